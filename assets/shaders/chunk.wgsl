@@ -0,0 +1,139 @@
+#import bevy_sprite::mesh2d_view_bindings
+#import bevy_sprite::mesh2d_bindings
+
+// NOTE: Bindings must come before functions that use them!
+#import bevy_sprite::mesh2d_functions
+
+@group(1) @binding(0)
+var<uniform> color: vec4<f32>;
+@group(1) @binding(1)
+var texture: texture_2d<f32>;
+@group(1) @binding(2)
+var texture_sampler: sampler;
+// Wetness and snow cover of the ground of each biome, MAX_WEATHER_BIOMES long
+@group(1) @binding(3)
+var<uniform> weather: array<vec4<f32>, 32>;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    // Position inside the quad in tiles, with v going down
+    @location(1) uv: vec2<f32>,
+    // Offset and size of the repeated atlas cell
+    @location(2) atlas_rect: vec4<f32>,
+    @location(3) tint: vec4<f32>,
+    // Frame count, frames per second and uv offset between frames
+    @location(4) animation: vec4<f32>,
+    // Random quarter turns in bit 0, random flip in bit 1 and the number of extra cells
+    // above them, then the seed of the tile hash
+    @location(5) variant: vec2<u32>,
+    // Uv offsets to the extra cells
+    @location(6) variant_cells: vec4<f32>,
+    @location(7) biome: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) atlas_rect: vec4<f32>,
+    @location(2) tint: vec4<f32>,
+    @location(3) frame_offset: vec2<f32>,
+    @location(4) @interpolate(flat) variant: vec2<u32>,
+    @location(5) world_position: vec2<f32>,
+    // Animated tiles, i.e. water, are not covered by puddles or snow
+    @location(6) weathered: f32,
+    @location(7) variant_cells: vec4<f32>,
+    @location(8) @interpolate(flat) biome: u32,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+
+    let world_position = mesh2d_position_local_to_world(mesh.model, vec4<f32>(vertex.position, 1.0));
+
+    out.clip_position = mesh2d_position_world_to_clip(world_position);
+    out.world_position = world_position.xy;
+    out.weathered = select(1.0, 0.0, vertex.animation.x > 1.0);
+    out.uv = vertex.uv;
+    out.atlas_rect = vertex.atlas_rect;
+    out.tint = vertex.tint;
+    out.variant = vertex.variant;
+    out.variant_cells = vertex.variant_cells;
+    out.biome = vertex.biome;
+
+    let frame = floor(globals.time * vertex.animation.y) % max(vertex.animation.x, 1.0);
+    out.frame_offset = frame * vertex.animation.zw;
+
+    return out;
+}
+
+// Value in [0, 1) hashed from a cell of the world
+fn hash(cell: vec2<f32>) -> f32 {
+    return fract(sin(dot(cell, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+// Value in [0, 1) hashed from a tile of the world and a seed, exact for any tile coordinate
+fn tile_hash(tile: vec2<i32>, seed: u32) -> f32 {
+    var h = seed ^ (bitcast<u32>(tile.x) * 0x8da6b343u) ^ (bitcast<u32>(tile.y) * 0xd8163841u);
+    h = (h ^ (h >> 16u)) * 0x7feb352du;
+    h = (h ^ (h >> 15u)) * 0x846ca68bu;
+    h = h ^ (h >> 16u);
+    return f32(h >> 8u) / 16777216.0;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    var cell_uv = fract(in.uv);
+
+    // Every tile of a quad picks its own cell, turns and flip, in that order of significance
+    let options = in.variant.x;
+    let turn_count = select(1u, 4u, (options & 1u) != 0u);
+    let flip_count = select(1u, 2u, (options & 2u) != 0u);
+    let count = ((options >> 2u) + 1u) * turn_count * flip_count;
+    let tile_coord = vec2<i32>(floor(in.world_position));
+    let index = min(u32(tile_hash(tile_coord, in.variant.y) * f32(count)), count - 1u);
+
+    var cell_offset = vec2<f32>(0.0);
+    let cell = index / (turn_count * flip_count);
+    if (cell == 1u) {
+        cell_offset = in.variant_cells.xy;
+    } else if (cell == 2u) {
+        cell_offset = in.variant_cells.zw;
+    }
+
+    if (index / turn_count % flip_count == 1u) {
+        cell_uv.x = 1.0 - cell_uv.x;
+    }
+
+    let turns = index % turn_count;
+    if (turns == 1u) {
+        cell_uv = vec2<f32>(1.0 - cell_uv.y, cell_uv.x);
+    } else if (turns == 2u) {
+        cell_uv = vec2<f32>(1.0 - cell_uv.x, 1.0 - cell_uv.y);
+    } else if (turns == 3u) {
+        cell_uv = vec2<f32>(cell_uv.y, 1.0 - cell_uv.x);
+    }
+
+    let uv = in.atlas_rect.xy + cell_uv * in.atlas_rect.zw + in.frame_offset + cell_offset;
+
+    var colour = textureSample(texture, texture_sampler, uv) * in.tint;
+
+    // Only biomes with a slot have ground weather
+    var ground = vec4<f32>(0.0);
+    if (in.biome < 32u) {
+        ground = weather[in.biome];
+    }
+
+    // Wet ground darkens, with puddles in some half tile cells as it gets wetter
+    let spot = hash(floor(in.world_position * 2.0));
+    let wetness = ground.x * in.weathered;
+    let puddle = select(0.0, wetness, spot < wetness * 0.3);
+    let wet = colour.rgb * (1.0 - 0.25 * wetness);
+    colour = vec4<f32>(mix(wet, vec3<f32>(0.3, 0.4, 0.6), puddle * 0.5), colour.a);
+
+    // Snow settles unevenly before covering the ground
+    let snow = clamp(ground.y * in.weathered * (0.6 + 0.8 * spot), 0.0, 1.0);
+    colour = mix(colour, vec4<f32>(0.93, 0.95, 1.0, colour.a), snow);
+
+    return colour * color;
+}
@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+use bevy::render::mesh::VertexAttributeValues;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use cli_mate::terrain::atlas::TileAtlas;
+use cli_mate::terrain::autotile::TileNeighbourhood;
+use cli_mate::terrain::chunk::Chunk;
+use cli_mate::terrain::meshing::MeshingMode;
+use cli_mate::terrain::seed::WorldSeed;
+use cli_mate::{TerrainDefinition, WorldGenerator};
+
+/// Chunks meshed by each benchmark, a square of this many chunks per side.
+const CHUNKS_PER_SIDE: i32 = 4;
+
+fn load_generator(
+    seed: WorldSeed
+) -> WorldGenerator {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/terrain/default.terrain.ron");
+    let bytes = std::fs::read(path).expect("Failed to read terrain definition");
+    let definition = TerrainDefinition::from_bytes(&bytes).expect("Invalid terrain definition");
+
+    WorldGenerator::from_definition(seed, &definition).expect("Invalid terrain definition")
+}
+
+fn generate_chunks(
+    generator: &WorldGenerator
+) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+
+    for x in 0..CHUNKS_PER_SIDE {
+        for y in 0..CHUNKS_PER_SIDE {
+            let chunk = generator.generate_chunk(IVec2::new(x, y)).expect("Failed to generate chunk");
+            chunks.push(Chunk::from_data(chunk));
+        }
+    }
+
+    chunks
+}
+
+fn vertex_count(
+    mesh: &Mesh
+) -> usize {
+    match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(VertexAttributeValues::Float32x3(positions)) => positions.len(),
+        _ => 0
+    }
+}
+
+fn meshing(c: &mut Criterion) {
+    let generator = load_generator(WorldSeed::new(0));
+    let biome_handle = generator.biome_handle();
+    let seed = generator.seed();
+    let tile_atlas = TileAtlas::default();
+    let chunks = generate_chunks(&generator);
+
+    for meshing_mode in [MeshingMode::Tiles, MeshingMode::Greedy] {
+        let vertices: usize = chunks.iter()
+            .map(|chunk| {
+                let neighbourhood = TileNeighbourhood::new(chunk, |_| None);
+                vertex_count(&chunk.generate_mesh(biome_handle, &neighbourhood, meshing_mode, &tile_atlas, seed))
+            })
+            .sum();
+
+        println!("{:?}: {} vertices over {} chunks, {:.1} per chunk",
+                 meshing_mode, vertices, chunks.len(), vertices as f32 / chunks.len() as f32);
+    }
+
+    let mut group = c.benchmark_group("generate_mesh");
+
+    for meshing_mode in [MeshingMode::Tiles, MeshingMode::Greedy] {
+        group.bench_function(format!("{:?}", meshing_mode), |b| b.iter(|| {
+            for chunk in chunks.iter() {
+                let neighbourhood = TileNeighbourhood::new(chunk, |_| None);
+                black_box(chunk.generate_mesh(biome_handle, &neighbourhood, meshing_mode, &tile_atlas, seed));
+            }
+        }));
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, meshing);
+criterion_main!(benches);
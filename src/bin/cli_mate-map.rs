@@ -0,0 +1,145 @@
+use std::env;
+use std::path::PathBuf;
+use std::process;
+use anyhow::{anyhow, bail, Context};
+use bevy::prelude::IVec2;
+use cli_mate::map::{MapColoring, MapOptions, MapPalette, render_map};
+use cli_mate::terrain::atlas::{TILE_ATLAS_PATH, TileAtlas};
+use cli_mate::terrain::definition::TERRAIN_DEFINITION_PATH;
+use cli_mate::terrain::season::Season;
+use cli_mate::terrain::seed::WorldSeed;
+use cli_mate::{TerrainDefinition, WorldGenerator};
+
+const USAGE: &str = "\
+Render a region of a world to a PNG without starting the game.
+
+Usage: cli_mate-map [options]
+
+Options:
+    --seed <seed>         World seed, a number or random [default: 0]
+    --from <x,y>          Lowest chunk of the region [default: -4,-4]
+    --to <x,y>            Highest chunk of the region, inclusive [default: 3,3]
+    --scale <pixels>      Pixels per tile side [default: 2]
+    --color <mode>        Colour tiles by `tiles` or `biomes` [default: tiles]
+    --season <season>     Season to generate the region in [default: spring]
+    --foliage             Mark tiles with foliage
+    --legend              Add a legend of the biomes, tiles and foliage in the map
+    --assets <dir>        Asset directory [default: assets]
+    --output <file>       PNG file to write [default: map.png]
+    --help                Print this message
+
+The map may be at most 16384 pixels per side.";
+
+struct Arguments {
+    seed: WorldSeed,
+    season: Season,
+    assets: PathBuf,
+    output: PathBuf,
+    options: MapOptions
+}
+
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("error: {:#}", error);
+        process::exit(1);
+    }
+}
+
+fn run() -> anyhow::Result<()> {
+    let Some(arguments) = parse_arguments(env::args().skip(1))? else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+
+    let definition_path = arguments.assets.join(TERRAIN_DEFINITION_PATH);
+    let definition_bytes = std::fs::read(&definition_path)
+        .with_context(|| format!("Failed to read {}", definition_path.display()))?;
+    let definition = TerrainDefinition::from_bytes(&definition_bytes)
+        .with_context(|| format!("Invalid terrain definition {}", definition_path.display()))?;
+
+    let atlas_path = arguments.assets.join(TILE_ATLAS_PATH);
+    let atlas_bytes = std::fs::read(&atlas_path)
+        .with_context(|| format!("Failed to read {}", atlas_path.display()))?;
+    let atlas = TileAtlas::from_bytes(&atlas_bytes)
+        .with_context(|| format!("Invalid tile atlas {}", atlas_path.display()))?;
+
+    let mut generator = WorldGenerator::from_definition(arguments.seed, &definition)?;
+    generator.set_season(arguments.season);
+
+    let palette = MapPalette::load(&arguments.assets, &definition, &atlas)?;
+    let map = render_map(&generator, &palette, &arguments.options)?;
+
+    map.save(&arguments.output)
+        .with_context(|| format!("Failed to write {}", arguments.output.display()))?;
+
+    println!("Wrote chunks {} to {} of seed {} to {} ({}x{})",
+             arguments.options.min_chunk, arguments.options.max_chunk, arguments.seed.seed,
+             arguments.output.display(), map.width(), map.height());
+
+    Ok(())
+}
+
+/// Parse the command line, `None` when help was asked for.
+fn parse_arguments(
+    mut args: impl Iterator<Item = String>
+) -> anyhow::Result<Option<Arguments>> {
+    let mut arguments = Arguments {
+        seed: WorldSeed::default(),
+        season: Season::default(),
+        assets: PathBuf::from("assets"),
+        output: PathBuf::from("map.png"),
+        options: MapOptions::default()
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| anyhow!("Missing value for {}", arg));
+
+        match arg.as_str() {
+            "--seed" => {
+                let value = value()?;
+                arguments.seed = WorldSeed::parse(&value)
+                    .ok_or_else(|| anyhow!("Invalid seed '{}'", value))?;
+            },
+            "--from" => arguments.options.min_chunk = parse_chunk(&value()?)?,
+            "--to" => arguments.options.max_chunk = parse_chunk(&value()?)?,
+            "--scale" => {
+                let value = value()?;
+                arguments.options.scale = value.trim().parse().ok()
+                    .filter(|scale| *scale > 0)
+                    .ok_or_else(|| anyhow!("Invalid scale '{}'", value))?;
+            },
+            "--color" => {
+                let value = value()?;
+                arguments.options.coloring = MapColoring::parse(&value)
+                    .ok_or_else(|| anyhow!("Invalid colour mode '{}', expected tiles or biomes", value))?;
+            },
+            "--season" => {
+                let value = value()?;
+                arguments.season = Season::parse(&value)
+                    .ok_or_else(|| anyhow!("Invalid season '{}'", value))?;
+            },
+            "--foliage" => arguments.options.foliage = true,
+            "--legend" => arguments.options.legend = true,
+            "--assets" => arguments.assets = PathBuf::from(value()?),
+            "--output" | "-o" => arguments.output = PathBuf::from(value()?),
+            "--help" | "-h" => return Ok(None),
+            _ => bail!("Unknown argument '{}'\n\n{}", arg, USAGE)
+        }
+    }
+
+    arguments.options.validate()?;
+
+    Ok(Some(arguments))
+}
+
+/// Parse a chunk coordinate written as `x,y`.
+fn parse_chunk(
+    value: &str
+) -> anyhow::Result<IVec2> {
+    let parse = || {
+        let (x, y) = value.split_once(',')?;
+        Some(IVec2::new(x.trim().parse().ok()?, y.trim().parse().ok()?))
+    };
+
+    parse().ok_or_else(|| anyhow!("Invalid chunk coordinate '{}', expected x,y", value))
+}
@@ -0,0 +1,219 @@
+use std::env;
+use std::f32::consts::TAU;
+use bevy::prelude::*;
+use crate::entities::player::Player;
+use crate::terrain::chunk_handler::{ChunkMaterialHandle, FoliageSprite};
+use crate::terrain::chunk_material::ChunkMaterial;
+
+/// Environment variable read for the day length when none is given on the command line.
+pub const DAY_LENGTH_ENV_VAR: &str = "CLI_MATE_DAY_LENGTH";
+
+/// Command line flag for the length of a day in seconds, i.e. `--day-length 120`.
+pub const DAY_LENGTH_ARG: &str = "--day-length";
+
+pub const DEFAULT_DAY_LENGTH_SECONDS: f32 = 600.0;
+
+/// Time of day of a new world, early morning.
+pub const START_TIME_OF_DAY: f32 = 0.3;
+
+/// Times of day as a fraction of the day, starting at midnight.
+pub const DAWN: f32 = 0.25;
+pub const DUSK: f32 = 0.75;
+
+/// Background colour at noon, darkened along with the world by `Daylight`.
+pub const CLEAR_COLOR: Color = Color::rgb(0.4, 0.4, 0.4);
+
+const NIGHT_COLOR: Color = Color::rgb(0.25, 0.3, 0.55);
+const TWILIGHT_COLOR: Color = Color::rgb(1.0, 0.7, 0.55);
+
+/// Smallest change in any channel of the daylight colour that is applied, so materials
+/// are not updated every frame.
+const DAYLIGHT_STEP: f32 = 1.0 / 255.0;
+
+pub struct ClockPlugin;
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        let world_clock = WorldClock::from_env();
+
+        app.insert_resource(Daylight(world_clock.daylight()))
+            .insert_resource(world_clock)
+            .add_event::<ClockEvent>()
+            .add_system(advance_clock)
+            .add_system(apply_daylight.after(advance_clock))
+            .add_system(apply_daylight_to_sprites.after(advance_clock));
+    }
+}
+
+/// Sent when the world clock passes dawn or dusk, with the number of the day.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ClockEvent {
+    Dawn { day: u32 },
+    Dusk { day: u32 }
+}
+
+/// In game time, independent of `Time` so days can be any length.
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+pub struct WorldClock {
+    /// Days passed since the world was created.
+    pub day: u32,
+    /// Fraction of the current day in [0, 1), starting at midnight.
+    pub time_of_day: f32,
+    /// Real seconds in a day.
+    pub day_length: f32
+}
+
+impl WorldClock {
+    pub fn new(
+        day_length: f32
+    ) -> Self {
+        WorldClock {
+            day: 0,
+            time_of_day: START_TIME_OF_DAY,
+            day_length
+        }
+    }
+
+    /// Read the day length from the `--day-length` argument, falling back to the
+    /// `CLI_MATE_DAY_LENGTH` environment variable and then to ten minutes.
+    pub fn from_env() -> Self {
+        let arg = env::args()
+            .skip_while(|arg| arg != DAY_LENGTH_ARG)
+            .nth(1);
+
+        let day_length = match arg.or_else(|| env::var(DAY_LENGTH_ENV_VAR).ok()) {
+            Some(value) => match value.trim().parse::<f32>() {
+                Ok(day_length) if day_length > 0.0 => day_length,
+                _ => {
+                    warn!("Invalid day length '{}', using {} seconds", value, DEFAULT_DAY_LENGTH_SECONDS);
+                    DEFAULT_DAY_LENGTH_SECONDS
+                }
+            },
+            None => DEFAULT_DAY_LENGTH_SECONDS
+        };
+
+        WorldClock::new(day_length)
+    }
+
+    pub fn is_day(&self) -> bool {
+        (DAWN..DUSK).contains(&self.time_of_day)
+    }
+
+    /// Move the clock forward, returning the dawns and dusks passed on the way.
+    pub fn advance(
+        &mut self,
+        seconds: f32
+    ) -> Vec<ClockEvent> {
+        let mut events = Vec::new();
+        let mut remaining = seconds.max(0.0) / self.day_length;
+
+        while remaining > 0.0 {
+            let to_midnight = 1.0 - self.time_of_day;
+            let step = remaining.min(to_midnight);
+            let end = self.time_of_day + step;
+
+            if self.time_of_day < DAWN && end >= DAWN {
+                events.push(ClockEvent::Dawn { day: self.day });
+            }
+
+            if self.time_of_day < DUSK && end >= DUSK {
+                events.push(ClockEvent::Dusk { day: self.day });
+            }
+
+            if step >= to_midnight {
+                self.day += 1;
+                self.time_of_day = 0.0;
+            } else {
+                self.time_of_day = end;
+            }
+
+            remaining -= step;
+        }
+
+        events
+    }
+
+    /// Colour the world is multiplied with, white at noon, blue at midnight and orange
+    /// around dawn and dusk.
+    pub fn daylight(&self) -> Color {
+        // -1 at midnight, 0 at dawn and dusk, 1 at noon
+        let sun_height = -(self.time_of_day * TAU).cos();
+
+        let day = ((sun_height + 0.4) / 0.8).clamp(0.0, 1.0);
+        let day = day * day * (3.0 - 2.0 * day);
+        let twilight = (1.0 - sun_height.abs() / 0.4).max(0.0) * 0.5;
+
+        let mix = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let channel = |night: f32, twilight_channel: f32| {
+            mix(mix(night, 1.0, day), twilight_channel, twilight)
+        };
+
+        Color::rgb(
+            channel(NIGHT_COLOR.r(), TWILIGHT_COLOR.r()),
+            channel(NIGHT_COLOR.g(), TWILIGHT_COLOR.g()),
+            channel(NIGHT_COLOR.b(), TWILIGHT_COLOR.b()))
+    }
+}
+
+/// Current lighting colour of the world, only updated when it changes noticeably.
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+pub struct Daylight(pub Color);
+
+pub fn advance_clock(
+    time: Res<Time>,
+    mut world_clock: ResMut<WorldClock>,
+    mut daylight: ResMut<Daylight>,
+    mut clock_events: EventWriter<ClockEvent>
+) {
+    clock_events.send_batch(world_clock.advance(time.delta_seconds()));
+
+    let colour = world_clock.daylight();
+    let difference = Vec4::from(colour.as_rgba_f32()) - Vec4::from(daylight.0.as_rgba_f32());
+
+    if difference.abs().max_element() >= DAYLIGHT_STEP {
+        daylight.0 = colour;
+    }
+}
+
+/// Tint the background and the shared chunk material with the daylight colour.
+fn apply_daylight(
+    daylight: Res<Daylight>,
+    mut clear_color: ResMut<ClearColor>,
+    chunk_material: Option<Res<ChunkMaterialHandle>>,
+    mut materials: ResMut<Assets<ChunkMaterial>>
+) {
+    if !daylight.is_changed() {
+        return;
+    }
+
+    clear_color.0 = multiply(CLEAR_COLOR, daylight.0);
+
+    if let Some(material) = chunk_material.and_then(|handle| materials.get_mut(&handle.0)) {
+        material.color = daylight.0;
+    }
+}
+
+/// Tint foliage and the player, foliage spawned since the last change is tinted as it appears.
+fn apply_daylight_to_sprites(
+    daylight: Res<Daylight>,
+    mut foliage: Query<(&mut Sprite, ChangeTrackers<FoliageSprite>)>,
+    mut players: Query<&mut TextureAtlasSprite, With<Player>>
+) {
+    for (mut sprite, foliage_tracker) in foliage.iter_mut() {
+        if daylight.is_changed() || foliage_tracker.is_added() {
+            sprite.color = daylight.0;
+        }
+    }
+
+    if daylight.is_changed() {
+        for mut sprite in players.iter_mut() {
+            sprite.color = daylight.0;
+        }
+    }
+}
+
+fn multiply(
+    a: Color,
+    b: Color
+) -> Color {
+    Color::rgba(a.r() * b.r(), a.g() * b.g(), a.b() * b.b(), a.a() * b.a())
+}
@@ -3,6 +3,7 @@ use bevy::{
 };
 
 use crate::entities::player::Player;
+use crate::weather::CurrentWeather;
 
 pub const SPEED: f32 = 100.0;
 pub const SIDE_SPEED_FACTOR: f32 = 1.;
@@ -10,7 +11,8 @@ pub const SIDE_SPEED_FACTOR: f32 = 1.;
 pub fn player_movement(
     keyboard_input: Res<Input<KeyCode>>,
     mut transforms: Query<(&mut Transform, &mut Player), With<Player>>,
-    time: Res<Time>
+    time: Res<Time>,
+    current_weather: Res<CurrentWeather>
 ) {
     for (mut transform, mut player) in transforms.iter_mut() {
         let player_pos = player.pos;
@@ -34,7 +36,7 @@ pub fn player_movement(
         // Update the players accelerations
         player.add_acc(Vec2::new(side, up));
 
-        player.update(time.delta_seconds());
+        player.update(time.delta_seconds(), &current_weather);
 
         transform.translation = player_pos.extend(2.0);
     }
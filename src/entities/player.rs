@@ -4,6 +4,7 @@ use bevy::{
 
 use crate::MainCamera;
 use crate::entities::control::player_movement;
+use crate::weather::CurrentWeather;
 
 #[derive(Component, Deref, DerefMut)]
 struct PlayerAnimationTimer(Timer);
@@ -96,12 +97,14 @@ pub struct Player {
 }
 
 impl Player {
-    pub fn update(&mut self, dt: f32) {
-        self.vel += dt * self.acc;
+    /// Move the player, bad weather slows the player down and wind pushes them along.
+    pub fn update(&mut self, dt: f32, weather: &CurrentWeather) {
+        self.vel += dt * self.acc * weather.movement_factor();
 
-        self.pos += self.vel * dt;
+        let movement = (self.vel + weather.wind) * dt;
+        self.pos += movement;
 
-        self.distance_moved += (dt * self.vel).length();
+        self.distance_moved += movement.length();
 
         self.vel -= self.vel * self.vel.length() * 0.9 * dt;
 
@@ -0,0 +1,14 @@
+use bevy::prelude::*;
+
+pub mod terrain;
+pub mod entities;
+pub mod save;
+pub mod clock;
+pub mod weather;
+pub mod map;
+
+pub use crate::terrain::generator::{ChunkData, WorldGenerator};
+pub use crate::terrain::definition::TerrainDefinition;
+
+#[derive(Component)]
+pub struct MainCamera;
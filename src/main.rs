@@ -1,18 +1,17 @@
 extern crate core;
 
-mod terrain;
-mod entities;
-
 use bevy::{
     prelude::*,
 };
 
 use bevy::window::PresentMode;
 use bevy_debug_text_overlay::OverlayPlugin;
-use crate::entities::player::PlayerPlugin;
-use crate::terrain::chunk_handler::ChunkHandlerPlugin;
-use crate::terrain::SurfacePlugin;
-use crate::terrain::terrain::TerrainPlugin;
+use cli_mate::MainCamera;
+use cli_mate::clock::{ClockPlugin, CLEAR_COLOR};
+use cli_mate::entities::player::PlayerPlugin;
+use cli_mate::save::SavePlugin;
+use cli_mate::terrain::SurfacePlugin;
+use cli_mate::weather::WeatherPlugin;
 
 fn main() {
     App::new()
@@ -24,18 +23,23 @@ fn main() {
                 ..default()
             },
             ..default()
-        }).set(ImagePlugin::default_nearest()))
-        .insert_resource(ClearColor(Color::rgb(0.4, 0.4, 0.4)))
+        }).set(ImagePlugin::default_nearest()).set(AssetPlugin {
+            // Hot reload terrain definitions and sprites while the game is running
+            watch_for_changes: true,
+            ..default()
+        }))
+        .insert_resource(ClearColor(CLEAR_COLOR))
         .add_plugin(OverlayPlugin { font_size: 22.0, ..default() })
+        // Before the terrain, it provides the seed and region store of the save slot
+        .add_plugin(SavePlugin)
         .add_plugin(SurfacePlugin)
         .add_plugin(PlayerPlugin)
+        .add_plugin(ClockPlugin)
+        .add_plugin(WeatherPlugin)
         .add_startup_system(setup)
         .run();
 }
 
-#[derive(Component)]
-pub struct MainCamera;
-
 fn setup(
     mut commands: Commands,
 ) {
@@ -0,0 +1,402 @@
+use std::path::Path;
+use anyhow::Context;
+use bevy::prelude::*;
+use image::{Rgb, RgbImage, RgbaImage};
+use crate::terrain::atlas::TileAtlas;
+use crate::terrain::chunk::CHUNK_SIZE;
+use crate::terrain::definition::TerrainDefinition;
+use crate::terrain::foliage::FoliageType;
+use crate::terrain::generator::WorldGenerator;
+
+/// Largest width and height of the map in pixels, not counting the legend.
+pub const MAX_MAP_SIZE: u32 = 16384;
+
+/// Pixels per font pixel of the legend text.
+const LEGEND_TEXT_SCALE: u32 = 2;
+const LEGEND_MARGIN: u32 = 8;
+const LEGEND_BACKGROUND: Rgb<u8> = Rgb([32, 32, 32]);
+const LEGEND_TEXT: Rgb<u8> = Rgb([230, 230, 230]);
+const MARKER_OUTLINE: Rgb<u8> = Rgb([16, 16, 16]);
+
+/// Size of a glyph of the legend font in font pixels, glyphs are one pixel apart.
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+
+/// What the pixels of each tile show.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum MapColoring {
+    /// The colour of the tile's atlas cell, tinted by its biome.
+    #[default]
+    Tiles,
+    /// The map colour of the tile's biome.
+    Biomes
+}
+
+impl MapColoring {
+    pub fn parse(
+        value: &str
+    ) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "tiles" | "tile" => Some(MapColoring::Tiles),
+            "biomes" | "biome" => Some(MapColoring::Biomes),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MapOptions {
+    /// Lowest chunk coordinate rendered.
+    pub min_chunk: IVec2,
+    /// Highest chunk coordinate rendered, inclusive.
+    pub max_chunk: IVec2,
+    /// Pixels per tile side.
+    pub scale: u32,
+    pub coloring: MapColoring,
+    /// Mark tiles with foliage with a square in the colour of the foliage sprite.
+    pub foliage: bool,
+    /// Add a legend of the biomes, tiles when coloured by tile, and foliage in the map to
+    /// its right.
+    pub legend: bool
+}
+
+impl Default for MapOptions {
+    fn default() -> Self {
+        MapOptions {
+            min_chunk: IVec2::splat(-4),
+            max_chunk: IVec2::splat(3),
+            scale: 2,
+            coloring: MapColoring::default(),
+            foliage: false,
+            legend: false
+        }
+    }
+}
+
+impl MapOptions {
+    /// Check the region can be rendered, returning the size of the map in pixels.
+    pub fn validate(&self) -> anyhow::Result<UVec2> {
+        anyhow::ensure!(self.scale > 0, "Map scale must be at least 1");
+        anyhow::ensure!(
+            self.max_chunk.x >= self.min_chunk.x && self.max_chunk.y >= self.min_chunk.y,
+            "Highest chunk {} is below lowest chunk {}", self.max_chunk, self.min_chunk);
+
+        // Widened, the pixel count of a huge region overflows before it could be allocated
+        let side = |min: i32, max: i32| (max as i64 - min as i64 + 1)
+            .checked_mul(CHUNK_SIZE as i64 * self.scale as i64)
+            .filter(|pixels| *pixels <= MAX_MAP_SIZE as i64);
+
+        match (side(self.min_chunk.x, self.max_chunk.x), side(self.min_chunk.y, self.max_chunk.y)) {
+            (Some(width), Some(height)) => Ok(UVec2::new(width as u32, height as u32)),
+            _ => anyhow::bail!(
+                "Chunks {} to {} at scale {} are larger than {} pixels per side, render a smaller region or scale",
+                self.min_chunk, self.max_chunk, self.scale, MAX_MAP_SIZE)
+        }
+    }
+}
+
+/// Map colours of every tile, biome and foliage type, indexed like the `BiomeHandle`.
+pub struct MapPalette {
+    pub tiles: Vec<Color>,
+    pub biomes: Vec<Color>,
+    pub foliage: Vec<Color>
+}
+
+impl MapPalette {
+    /// Tiles take the average colour of their atlas cell and foliage of its sprite, both
+    /// read from the `assets` directory. Biomes use their `map_color`.
+    pub fn load(
+        assets: &Path,
+        definition: &TerrainDefinition,
+        atlas: &TileAtlas
+    ) -> anyhow::Result<Self> {
+        let texture = read_image(&assets.join(&atlas.texture))?;
+
+        let tiles = definition.tiles.iter()
+            .map(|tile| {
+                let origin = atlas.cell_origin(tile.atlas_index);
+                average_color(&texture, origin, UVec2::splat(atlas.cell_size))
+            })
+            .collect();
+
+        let foliage = definition.foliage.iter()
+            .map(|foliage| {
+                let sprite = read_image(&assets.join(&foliage.sprite))?;
+                Ok(average_color(&sprite, UVec2::ZERO, UVec2::new(sprite.width(), sprite.height())))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let biomes = definition.biomes.iter().enumerate()
+            .map(|(index, biome)| match biome.map_color {
+                Some([r, g, b]) => Color::rgb(r, g, b),
+                // Golden ratio steps keep neighbouring indices far apart in hue
+                None => Color::hsl((index as f32 * 0.618_034).fract() * 360.0, 0.6, 0.5)
+            })
+            .collect();
+
+        Ok(MapPalette {
+            tiles,
+            biomes,
+            foliage
+        })
+    }
+}
+
+/// Generate every chunk in the rectangle of `options` and draw it, north up.
+pub fn render_map(
+    generator: &WorldGenerator,
+    palette: &MapPalette,
+    options: &MapOptions
+) -> anyhow::Result<RgbImage> {
+    let biome_handle = generator.biome_handle();
+    let map_size = options.validate()?;
+    let scale = options.scale;
+    let chunks = map_size / (CHUNK_SIZE as u32 * scale);
+
+    let mut tiles_used = vec![false; palette.tiles.len()];
+    let mut biomes_used = vec![false; palette.biomes.len()];
+    let mut foliage_used = vec![false; palette.foliage.len()];
+
+    let mut map = RgbImage::from_pixel(map_size.x, map_size.y, LEGEND_BACKGROUND);
+
+    for chunk_x in 0..chunks.x {
+        for chunk_y in 0..chunks.y {
+            let coordinate = options.min_chunk + UVec2::new(chunk_x, chunk_y).as_ivec2();
+            let chunk = generator.generate_chunk(coordinate)?;
+
+            for x in 0..CHUNK_SIZE {
+                for y in 0..CHUNK_SIZE {
+                    let tile = chunk.tiles[x][y];
+                    biomes_used[tile.biome.0 as usize] = true;
+
+                    let color = match options.coloring {
+                        MapColoring::Tiles => {
+                            tiles_used[tile.tile] = true;
+                            let color = palette.tiles[tile.tile];
+                            if biome_handle.tiles[tile.tile].tinted {
+                                multiply(color, biome_handle.get_biome(tile.biome)?.tint)
+                            } else {
+                                color
+                            }
+                        },
+                        MapColoring::Biomes => palette.biomes[tile.biome.0 as usize]
+                    };
+
+                    // Image rows go down while tile y goes up
+                    let pixel_x = (chunk_x * CHUNK_SIZE as u32 + x as u32) * scale;
+                    let pixel_y = map_size.y - (chunk_y * CHUNK_SIZE as u32 + y as u32 + 1) * scale;
+                    fill(&mut map, UVec2::new(pixel_x, pixel_y), UVec2::splat(scale), to_rgb(color));
+
+                    let foliage = chunk.foliage_type[x][y];
+                    if options.foliage && foliage != FoliageType::NONE {
+                        foliage_used[foliage.0 as usize] = true;
+                        draw_marker(&mut map, UVec2::new(pixel_x, pixel_y), scale, to_rgb(palette.foliage[foliage.0 as usize]));
+                    }
+                }
+            }
+        }
+    }
+
+    if !options.legend {
+        return Ok(map);
+    }
+
+    let mut entries = used_entries(&biomes_used, &palette.biomes, |index| &biome_handle.biomes[index].id);
+    entries.extend(used_entries(&tiles_used, &palette.tiles, |index| &biome_handle.tiles[index].id));
+    entries.extend(used_entries(&foliage_used, &palette.foliage, |index| &biome_handle.foliage[index].id));
+
+    Ok(add_legend(map, &entries))
+}
+
+/// Names and colours of the entries that appear in the map.
+fn used_entries<'a>(
+    used: &[bool],
+    colors: &[Color],
+    id: impl Fn(usize) -> &'a String
+) -> Vec<(&'a str, Color)> {
+    used.iter().enumerate()
+        .filter(|(_, used)| **used)
+        .map(|(index, _)| (id(index).as_str(), colors[index]))
+        .collect()
+}
+
+/// Extend the map to the right with a swatch and the name of each entry.
+fn add_legend(
+    map: RgbImage,
+    entries: &[(&str, Color)]
+) -> RgbImage {
+    let line_height = (GLYPH_HEIGHT + 2) * LEGEND_TEXT_SCALE;
+    let swatch = GLYPH_HEIGHT * LEGEND_TEXT_SCALE;
+    let longest = entries.iter().map(|(name, _)| name.len() as u32).max().unwrap_or(0);
+
+    let legend_width = LEGEND_MARGIN * 2 + swatch + LEGEND_TEXT_SCALE * 2 + longest * (GLYPH_WIDTH + 1) * LEGEND_TEXT_SCALE;
+    let legend_height = LEGEND_MARGIN * 2 + entries.len() as u32 * line_height;
+
+    let mut image = RgbImage::from_pixel(
+        map.width() + legend_width,
+        map.height().max(legend_height),
+        LEGEND_BACKGROUND);
+    image::imageops::replace(&mut image, &map, 0, 0);
+
+    for (line, (name, color)) in entries.iter().enumerate() {
+        let origin = UVec2::new(map.width() + LEGEND_MARGIN, LEGEND_MARGIN + line as u32 * line_height);
+
+        fill(&mut image, origin, UVec2::splat(swatch), to_rgb(*color));
+        draw_text(&mut image, origin + UVec2::new(swatch + LEGEND_TEXT_SCALE * 2, 0), name);
+    }
+
+    image
+}
+
+/// Square in the middle of a tile, outlined when the tile is large enough to show it.
+fn draw_marker(
+    image: &mut RgbImage,
+    tile_origin: UVec2,
+    scale: u32,
+    color: Rgb<u8>
+) {
+    let size = (scale / 2).max(1);
+    let origin = tile_origin + (scale - size) / 2;
+
+    if scale >= 6 {
+        fill(image, origin - 1, UVec2::splat(size + 2), MARKER_OUTLINE);
+    }
+
+    fill(image, origin, UVec2::splat(size), color);
+}
+
+fn draw_text(
+    image: &mut RgbImage,
+    origin: UVec2,
+    text: &str
+) {
+    for (index, character) in text.chars().enumerate() {
+        let glyph_origin = origin + UVec2::new(index as u32 * (GLYPH_WIDTH + 1) * LEGEND_TEXT_SCALE, 0);
+
+        for (row, bits) in glyph(character).iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                    let pixel = glyph_origin + UVec2::new(column, row as u32) * LEGEND_TEXT_SCALE;
+                    fill(image, pixel, UVec2::splat(LEGEND_TEXT_SCALE), LEGEND_TEXT);
+                }
+            }
+        }
+    }
+}
+
+/// Rows of a 3x5 pixel glyph, top first, with the leftmost pixel in the highest bit.
+/// Letters are drawn in upper case.
+fn glyph(
+    character: char
+) -> [u8; 5] {
+    match character.to_ascii_lowercase() {
+        'a' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'b' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'c' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'd' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'e' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'f' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'g' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'h' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'i' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'j' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'k' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'l' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'm' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'n' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'o' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'p' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'r' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        's' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        't' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'u' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'v' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'w' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'x' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ' ' => [0b000; 5],
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010]
+    }
+}
+
+/// Fill a rectangle, clipped to the image.
+fn fill(
+    image: &mut RgbImage,
+    origin: UVec2,
+    size: UVec2,
+    color: Rgb<u8>
+) {
+    let max = (origin + size).min(UVec2::new(image.width(), image.height()));
+
+    for x in origin.x..max.x {
+        for y in origin.y..max.y {
+            image.put_pixel(x, y, color);
+        }
+    }
+}
+
+fn read_image(
+    path: &Path
+) -> anyhow::Result<RgbaImage> {
+    Ok(image::open(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .into_rgba8())
+}
+
+/// Average colour of the opaque pixels in a rectangle, weighted by their alpha.
+fn average_color(
+    image: &RgbaImage,
+    origin: UVec2,
+    size: UVec2
+) -> Color {
+    let max = (origin + size).min(UVec2::new(image.width(), image.height()));
+    let mut sum = Vec3::ZERO;
+    let mut weight = 0.0;
+
+    for x in origin.x..max.x {
+        for y in origin.y..max.y {
+            let [r, g, b, a] = image.get_pixel(x, y).0;
+            let alpha = a as f32 / 255.0;
+
+            sum += Vec3::new(r as f32, g as f32, b as f32) / 255.0 * alpha;
+            weight += alpha;
+        }
+    }
+
+    if weight > 0.0 {
+        Color::rgb(sum.x / weight, sum.y / weight, sum.z / weight)
+    } else {
+        Color::FUCHSIA
+    }
+}
+
+fn multiply(
+    a: Color,
+    b: Color
+) -> Color {
+    Color::rgb(a.r() * b.r(), a.g() * b.g(), a.b() * b.b())
+}
+
+fn to_rgb(
+    color: Color
+) -> Rgb<u8> {
+    let [r, g, b, _] = color.as_rgba_f32();
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    Rgb([channel(r), channel(g), channel(b)])
+}
@@ -0,0 +1,412 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use crate::MainCamera;
+use crate::clock::{START_TIME_OF_DAY, WorldClock};
+use crate::entities::player::Player;
+use crate::terrain::biome::BiomeHandle;
+use crate::terrain::chunk_handler::ChunkHandler;
+use crate::terrain::region::RegionStore;
+use crate::terrain::seed::WorldSeed;
+
+/// Current save format, bumped whenever `SaveMetadata` or `WorldSave` change shape.
+pub const SAVE_FORMAT_VERSION: u32 = 2;
+
+pub const SAVE_DIRECTORY: &str = "saves";
+pub const DEFAULT_SAVE_SLOT: &str = "world";
+
+/// Command line flag and environment variable used to pick the save slot.
+pub const SAVE_ARG: &str = "--save";
+pub const SAVE_ENV_VAR: &str = "CLI_MATE_SAVE";
+
+pub const AUTOSAVE_INTERVAL_SECONDS: f32 = 300.0;
+
+pub const SAVE_KEY: KeyCode = KeyCode::F5;
+pub const LOAD_KEY: KeyCode = KeyCode::F9;
+
+const METADATA_FILE: &str = "meta.ron";
+const WORLD_FILE: &str = "world.ron";
+const REGION_DIRECTORY: &str = "regions";
+
+pub struct SavePlugin;
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        let save_slot = SaveSlot::from_env();
+
+        app.insert_resource(RegionStore::new(save_slot.region_directory()))
+            .insert_resource(AutosaveTimer(Timer::from_seconds(AUTOSAVE_INTERVAL_SECONDS, TimerMode::Repeating)));
+
+        // Resume the slot if it has been saved before, the saved seed replaces any seed
+        // given on the command line.
+        let mut world_seed = WorldSeed::from_env();
+
+        if save_slot.exists() {
+            match save_slot.load() {
+                Ok(save_game) => {
+                    info!("Loading save slot '{}'", save_slot.name);
+                    world_seed = WorldSeed::new(save_game.metadata.seed);
+                    app.insert_resource(PendingLoad(Some(save_game.world)));
+                }
+                Err(error) => error!("Failed to load save slot '{}': {}", save_slot.name, error)
+            }
+        }
+
+        app.insert_resource(world_seed);
+
+        app.insert_resource(save_slot)
+            .init_resource::<PendingLoad>()
+            .add_startup_system_to_stage(StartupStage::PostStartup, apply_pending_load)
+            .add_system(quicksave)
+            .add_system(quickload)
+            .add_system(autosave)
+            .add_system_to_stage(CoreStage::Last, save_on_exit);
+    }
+}
+
+/// Named save slot, stored in its own directory under `saves/`.
+#[derive(Resource, Clone)]
+pub struct SaveSlot {
+    pub name: String
+}
+
+impl SaveSlot {
+    pub fn new(
+        name: impl Into<String>
+    ) -> Self {
+        SaveSlot {
+            name: name.into()
+        }
+    }
+
+    /// Read the slot name from the `--save` argument, falling back to the `CLI_MATE_SAVE`
+    /// environment variable and then to the default slot.
+    pub fn from_env() -> Self {
+        let arg = env::args()
+            .skip_while(|arg| arg != SAVE_ARG)
+            .nth(1);
+
+        let name = arg
+            .or_else(|| env::var(SAVE_ENV_VAR).ok())
+            .unwrap_or_else(|| DEFAULT_SAVE_SLOT.to_string());
+
+        SaveSlot::new(name)
+    }
+
+    pub fn directory(&self) -> PathBuf {
+        Path::new(SAVE_DIRECTORY).join(&self.name)
+    }
+
+    pub fn region_directory(&self) -> PathBuf {
+        self.directory().join(REGION_DIRECTORY)
+    }
+
+    pub fn exists(&self) -> bool {
+        self.directory().join(METADATA_FILE).exists()
+    }
+
+    pub fn load(&self) -> io::Result<SaveGame> {
+        let metadata: SaveMetadata = read_ron(&self.directory().join(METADATA_FILE))?;
+        let world: WorldSave = read_ron(&self.directory().join(WORLD_FILE))?;
+
+        migrate(SaveGame {
+            metadata,
+            world
+        })
+    }
+
+    pub fn save(
+        &self,
+        save_game: &SaveGame
+    ) -> io::Result<()> {
+        fs::create_dir_all(self.directory())?;
+
+        write_ron(&self.directory().join(WORLD_FILE), &save_game.world)?;
+
+        // Metadata is written last, a slot only counts as saved once it exists.
+        write_ron(&self.directory().join(METADATA_FILE), &save_game.metadata)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SaveMetadata {
+    pub version: u32,
+    pub name: String,
+    pub seed: u32,
+    /// Seconds since the unix epoch.
+    pub saved_at: u64
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PlayerSave {
+    pub pos: [f32; 2],
+    pub vel: [f32; 2]
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WorldSave {
+    pub player: PlayerSave,
+    pub camera_zoom: f32,
+    /// `WorldClock::day`, missing from version 1 saves.
+    #[serde(default)]
+    pub day: u32,
+    /// `WorldClock::time_of_day`, missing from version 1 saves.
+    #[serde(default)]
+    pub time_of_day: f32
+}
+
+/// Everything in a save slot apart from the region files, which the `RegionStore`
+/// reads and writes directly.
+pub struct SaveGame {
+    pub metadata: SaveMetadata,
+    pub world: WorldSave
+}
+
+/// Upgrade a save written by an older version of the game to `SAVE_FORMAT_VERSION`.
+pub fn migrate(
+    save_game: SaveGame
+) -> io::Result<SaveGame> {
+    let version = save_game.metadata.version;
+
+    if version > SAVE_FORMAT_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Save format version {} is newer than supported version {}", version, SAVE_FORMAT_VERSION)));
+    }
+
+    // Each older format version gets a step here that upgrades it by one version.
+    match version {
+        SAVE_FORMAT_VERSION => Ok(save_game),
+        // Version 1 did not save the clock, resume on the first morning like a new world.
+        1 => migrate(SaveGame {
+            metadata: SaveMetadata {
+                version: 2,
+                ..save_game.metadata
+            },
+            world: WorldSave {
+                day: 0,
+                time_of_day: START_TIME_OF_DAY,
+                ..save_game.world
+            }
+        }),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("No migration from save format version {}", version)))
+    }
+}
+
+#[derive(Resource, Deref, DerefMut)]
+struct AutosaveTimer(Timer);
+
+/// World state loaded at startup, applied once the player and camera have been spawned.
+#[derive(Resource, Default)]
+struct PendingLoad(Option<WorldSave>);
+
+fn read_ron<T: DeserializeOwned>(
+    path: &Path
+) -> io::Result<T> {
+    let contents = fs::read_to_string(path)?;
+
+    ron::from_str(&contents)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+fn write_ron<T: Serialize>(
+    path: &Path,
+    value: &T
+) -> io::Result<()> {
+    let contents = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+    let temp_path = path.with_extension("ron.tmp");
+    fs::write(&temp_path, contents)?;
+    fs::rename(temp_path, path)
+}
+
+fn save_world(
+    save_slot: &SaveSlot,
+    world_seed: &WorldSeed,
+    world: WorldSave,
+    chunk_handler: &mut ChunkHandler,
+    region_store: &RegionStore,
+    biome_handle: &BiomeHandle
+) -> io::Result<()> {
+    let modified_chunks = chunk_handler.chunks.values()
+        .filter(|chunk| chunk.modified)
+        .map(|chunk| (chunk.coordinate, chunk.to_save(biome_handle)))
+        .chain(chunk_handler.unsaved_chunks.iter()
+            .map(|(coordinate, chunk_save)| (*coordinate, chunk_save.clone())))
+        .collect();
+    region_store.save_chunks(modified_chunks)?;
+
+    let saved_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    save_slot.save(&SaveGame {
+        metadata: SaveMetadata {
+            version: SAVE_FORMAT_VERSION,
+            name: save_slot.name.clone(),
+            seed: world_seed.seed,
+            saved_at
+        },
+        world
+    })?;
+
+    // Chunks stay dirty until the whole slot is saved, so a failed save is retried
+    chunk_handler.unsaved_chunks.clear();
+    for chunk in chunk_handler.chunks.values_mut() {
+        chunk.modified = false;
+    }
+
+    Ok(())
+}
+
+fn world_save(
+    player: &Player,
+    camera_zoom: f32,
+    world_clock: &WorldClock
+) -> WorldSave {
+    WorldSave {
+        player: PlayerSave {
+            pos: player.pos.to_array(),
+            vel: player.vel.to_array()
+        },
+        camera_zoom,
+        day: world_clock.day,
+        time_of_day: world_clock.time_of_day
+    }
+}
+
+fn apply_world_save(
+    world_save: &WorldSave,
+    player: &mut Player,
+    transform: &mut Transform,
+    projection: &mut OrthographicProjection,
+    world_clock: &mut WorldClock
+) {
+    player.pos = Vec2::from_array(world_save.player.pos);
+    player.vel = Vec2::from_array(world_save.player.vel);
+    player.distance_moved = 0.0;
+    transform.translation = player.pos.extend(transform.translation.z);
+
+    projection.scale = world_save.camera_zoom;
+
+    world_clock.day = world_save.day;
+    world_clock.time_of_day = world_save.time_of_day;
+}
+
+fn apply_pending_load(
+    mut pending_load: ResMut<PendingLoad>,
+    mut world_clock: ResMut<WorldClock>,
+    mut players: Query<(&mut Player, &mut Transform), Without<MainCamera>>,
+    mut cameras: Query<&mut OrthographicProjection, With<MainCamera>>
+) {
+    let Some(world_save) = pending_load.0.take() else {
+        return;
+    };
+
+    for (mut player, mut transform) in players.iter_mut() {
+        for mut projection in cameras.iter_mut() {
+            apply_world_save(&world_save, &mut player, &mut transform, &mut projection, &mut world_clock);
+        }
+    }
+}
+
+type SaveSystemParams<'w, 's> = (
+    Res<'w, SaveSlot>,
+    Res<'w, WorldSeed>,
+    ResMut<'w, ChunkHandler>,
+    Res<'w, RegionStore>,
+    Res<'w, BiomeHandle>,
+    Res<'w, WorldClock>,
+    Query<'w, 's, &'static Player>,
+    Query<'w, 's, &'static OrthographicProjection, With<MainCamera>>
+);
+
+fn save_current_world(
+    (save_slot, world_seed, mut chunk_handler, region_store, biome_handle, world_clock, players, cameras): SaveSystemParams
+) {
+    let (Ok(player), Ok(projection)) = (players.get_single(), cameras.get_single()) else {
+        return;
+    };
+
+    let world = world_save(player, projection.scale, &world_clock);
+
+    match save_world(&save_slot, &world_seed, world, &mut chunk_handler, &region_store, &biome_handle) {
+        Ok(()) => info!("Saved world to slot '{}'", save_slot.name),
+        Err(error) => error!("Failed to save world to slot '{}': {}", save_slot.name, error)
+    }
+}
+
+fn quicksave(
+    keyboard_input: Res<Input<KeyCode>>,
+    save_params: SaveSystemParams
+) {
+    if keyboard_input.just_pressed(SAVE_KEY) {
+        save_current_world(save_params);
+    }
+}
+
+fn quickload(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    save_slot: Res<SaveSlot>,
+    mut chunk_handler: ResMut<ChunkHandler>,
+    mut world_clock: ResMut<WorldClock>,
+    mut players: Query<(&mut Player, &mut Transform), Without<MainCamera>>,
+    mut cameras: Query<&mut OrthographicProjection, With<MainCamera>>
+) {
+    if !keyboard_input.just_pressed(LOAD_KEY) {
+        return;
+    }
+
+    let save_game = match save_slot.load() {
+        Ok(save_game) => save_game,
+        Err(error) => {
+            error!("Failed to load save slot '{}': {}", save_slot.name, error);
+            return;
+        }
+    };
+
+    commands.insert_resource(WorldSeed::new(save_game.metadata.seed));
+
+    for (mut player, mut transform) in players.iter_mut() {
+        for mut projection in cameras.iter_mut() {
+            apply_world_save(&save_game.world, &mut player, &mut transform, &mut projection, &mut world_clock);
+        }
+    }
+
+    // Drop every loaded and unsaved chunk, they are reloaded from the slot's region files
+    // or regenerated from the saved seed.
+    for entity in chunk_handler.clear() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    info!("Loaded save slot '{}'", save_slot.name);
+}
+
+fn autosave(
+    time: Res<Time>,
+    mut timer: ResMut<AutosaveTimer>,
+    save_params: SaveSystemParams
+) {
+    if timer.tick(time.delta()).just_finished() {
+        save_current_world(save_params);
+    }
+}
+
+fn save_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    save_params: SaveSystemParams
+) {
+    if exit_events.iter().next().is_some() {
+        save_current_world(save_params);
+    }
+}
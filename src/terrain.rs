@@ -1,5 +1,8 @@
-use bevy::app::Plugin;
-use crate::{App, ChunkHandlerPlugin, TerrainPlugin};
+use bevy::app::{App, Plugin};
+use crate::terrain::chunk_handler::ChunkHandlerPlugin;
+use crate::terrain::season::SeasonPlugin;
+use crate::terrain::terrain::TerrainPlugin;
+use crate::terrain::tile_editor::TileEditorPlugin;
 
 pub mod meshing;
 pub mod chunk_handler;
@@ -9,12 +12,25 @@ pub mod biome;
 pub mod terrain;
 pub mod foliage;
 pub mod tile;
+pub mod seed;
+pub mod region;
+pub mod tile_editor;
+pub mod definition;
+pub mod climate;
+pub mod biome_map;
+pub mod autotile;
+pub mod chunk_material;
+pub mod atlas;
+pub mod season;
+pub mod generator;
 
 pub struct SurfacePlugin;
 impl Plugin for SurfacePlugin {
     fn build(&self, app: &mut App) {
         app
             .add_plugin(TerrainPlugin)
-            .add_plugin(ChunkHandlerPlugin);
+            .add_plugin(ChunkHandlerPlugin)
+            .add_plugin(TileEditorPlugin)
+            .add_plugin(SeasonPlugin);
     }
 }
\ No newline at end of file
@@ -0,0 +1,145 @@
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+use crate::terrain::definition::{TerrainDefinitionError, TileDefinition};
+
+/// Asset path of the grid layout of `tiles/tiles.png`.
+pub const TILE_ATLAS_PATH: &str = "tiles/tiles.atlas.ron";
+
+/// Grid layout of the tile texture atlas, loaded from a `.atlas.ron` file next to the texture.
+///
+/// Cells are `cell_size` pixels square with `padding` pixels between them, and are numbered
+/// by rows from the top left of the texture. Tiles refer to cells by their `atlas_index`.
+#[derive(Resource, Deserialize, TypeUuid, Clone, Debug, PartialEq)]
+#[uuid = "b4e1f7c2-9d3a-4c86-a5f0-6e2d8b1c7a34"]
+pub struct TileAtlas {
+    pub texture: String,
+    pub cell_size: u32,
+    pub columns: u32,
+    pub rows: u32,
+    #[serde(default)]
+    pub padding: u32
+}
+
+impl Default for TileAtlas {
+    fn default() -> Self {
+        TileAtlas {
+            texture: "tiles/tiles.png".to_string(),
+            cell_size: 16,
+            columns: 8,
+            rows: 16,
+            padding: 0
+        }
+    }
+}
+
+impl TileAtlas {
+    /// Parse and check a grid layout from the contents of a `.atlas.ron` file.
+    pub fn from_bytes(
+        bytes: &[u8]
+    ) -> Result<Self, anyhow::Error> {
+        let atlas: TileAtlas = ron::de::from_bytes(bytes)?;
+        anyhow::ensure!(
+            atlas.cell_size > 0 && atlas.columns > 0 && atlas.rows > 0,
+            "Tile atlas grid is empty");
+
+        Ok(atlas)
+    }
+
+    /// Number of cells in the grid.
+    pub fn cell_count(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+
+    /// Check that the highest cell every tile can reach is in the grid, counting its variant
+    /// cells, autotile set and the last frame of its animation. Cells past the end would
+    /// sample outside the texture.
+    pub fn check_tiles(
+        &self,
+        tiles: &[TileDefinition]
+    ) -> Result<(), TerrainDefinitionError> {
+        for tile in tiles {
+            let variant_cells = tile.variants.iter().flat_map(|variants| variants.cells.iter().copied());
+            let autotile_cell = tile.autotile.as_ref()
+                .map(|autotile| autotile.atlas_start + autotile.mode.cell_count() - 1);
+            let last_frame = tile.animation
+                .map(|animation| animation.frame_offset * animation.frames.saturating_sub(1) as usize)
+                .unwrap_or_default();
+
+            let cell = std::iter::once(tile.atlas_index)
+                .chain(variant_cells)
+                .chain(autotile_cell)
+                .max()
+                .unwrap_or_default() + last_frame;
+
+            if cell >= self.cell_count() {
+                return Err(TerrainDefinitionError::InvalidAtlasCell {
+                    tile: tile.id.clone(),
+                    cell,
+                    cell_count: self.cell_count()
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Size of the texture in pixels, assuming the grid covers the whole texture.
+    pub fn texture_size(&self) -> Vec2 {
+        let size = |cells: u32| cells * self.cell_size + cells.saturating_sub(1) * self.padding;
+
+        Vec2::new(size(self.columns) as f32, size(self.rows) as f32)
+    }
+
+    /// Pixel position of the top left corner of a cell in the texture.
+    pub fn cell_origin(
+        &self,
+        cell: usize
+    ) -> UVec2 {
+        let column = cell as u32 % self.columns;
+        let row = cell as u32 / self.columns;
+
+        UVec2::new(column, row) * (self.cell_size + self.padding)
+    }
+
+    /// Uv rectangle of a cell, inset by half a texel so nearest sampling never picks up
+    /// the neighbouring cell at the edges.
+    pub fn cell_rect(
+        &self,
+        cell: usize
+    ) -> Rect {
+        let texture_size = self.texture_size();
+        let min = self.cell_origin(cell).as_vec2();
+        let max = min + self.cell_size as f32;
+
+        Rect {
+            min: (min + 0.5) / texture_size,
+            max: (max - 0.5) / texture_size
+        }
+    }
+
+}
+
+#[derive(Default)]
+pub struct TileAtlasLoader;
+
+impl AssetLoader for TileAtlasLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let atlas = TileAtlas::from_bytes(bytes)?;
+
+            load_context.set_default_asset(LoadedAsset::new(atlas));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["atlas.ron"]
+    }
+}
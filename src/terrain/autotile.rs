@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+use crate::terrain::chunk::{Chunk, CHUNK_SIZE, NEIGHBOUR_OFFSETS};
+use crate::terrain::definition::AutotileMode;
+
+const NORTH: u8 = 1;
+const NORTH_EAST: u8 = 2;
+const EAST: u8 = 4;
+const SOUTH_EAST: u8 = 8;
+const SOUTH: u8 = 16;
+const SOUTH_WEST: u8 = 32;
+const WEST: u8 = 64;
+const NORTH_WEST: u8 = 128;
+
+/// Cell of each 8 bit neighbour mask in a blob tile set, the cells are the 47 reduced masks
+/// in ascending order.
+const BLOB_CELLS: [u8; 256] = blob_cells();
+
+/// Corners only change the look of a tile when both edges next to them connect.
+const fn reduce_blob_mask(
+    mask: u8
+) -> u8 {
+    let mut mask = mask;
+    let corners = [
+        (NORTH_EAST, NORTH, EAST),
+        (SOUTH_EAST, SOUTH, EAST),
+        (SOUTH_WEST, SOUTH, WEST),
+        (NORTH_WEST, NORTH, WEST)
+    ];
+
+    let mut i = 0;
+    while i < corners.len() {
+        let (corner, a, b) = corners[i];
+        if mask & a == 0 || mask & b == 0 {
+            mask &= !corner;
+        }
+        i += 1;
+    }
+
+    mask
+}
+
+const fn blob_cells() -> [u8; 256] {
+    let mut is_reduced = [false; 256];
+    let mut mask = 0;
+    while mask < 256 {
+        is_reduced[reduce_blob_mask(mask as u8) as usize] = true;
+        mask += 1;
+    }
+
+    let mut reduced_cell = [0u8; 256];
+    let mut cell = 0;
+    let mut mask = 0;
+    while mask < 256 {
+        if is_reduced[mask] {
+            reduced_cell[mask] = cell;
+            cell += 1;
+        }
+        mask += 1;
+    }
+
+    let mut cells = [0u8; 256];
+    let mut mask = 0;
+    while mask < 256 {
+        cells[mask] = reduced_cell[reduce_blob_mask(mask as u8) as usize];
+        mask += 1;
+    }
+
+    cells
+}
+
+/// Autotiling rules of a tile type, resolved from an `AutotileDefinition`.
+#[derive(Clone, Debug)]
+pub struct Autotile {
+    pub mode: AutotileMode,
+    pub atlas_start: usize,
+    /// Tile types counted as connected besides the tile type itself.
+    pub connects_to: Vec<usize>
+}
+
+impl Autotile {
+    /// Atlas cell of the tile at `x`, `y` picked from which of its neighbours connect.
+    pub fn atlas_index(
+        &self,
+        tile_type: usize,
+        neighbourhood: &TileNeighbourhood,
+        x: usize,
+        y: usize
+    ) -> usize {
+        let mut mask = 0u8;
+
+        // Bits of the mask follow the neighbour order, clockwise starting north
+        for (bit, offset) in NEIGHBOUR_OFFSETS.iter().enumerate() {
+            let neighbour = neighbourhood.get(x as i32 + offset.x, y as i32 + offset.y);
+
+            // Unloaded neighbours connect, so chunk borders don't flicker as chunks load
+            let connects = match neighbour {
+                Some(neighbour) => neighbour == tile_type || self.connects_to.contains(&neighbour),
+                None => true
+            };
+
+            if connects {
+                mask |= 1 << bit;
+            }
+        }
+
+        match self.mode {
+            AutotileMode::Edges => {
+                let edges = (mask & NORTH != 0) as usize
+                    | ((mask & EAST != 0) as usize) << 1
+                    | ((mask & SOUTH != 0) as usize) << 2
+                    | ((mask & WEST != 0) as usize) << 3;
+
+                self.atlas_start + edges
+            }
+            AutotileMode::Blob => self.atlas_start + BLOB_CELLS[mask as usize] as usize
+        }
+    }
+}
+
+/// Tile types of a chunk and the ring of tiles around it, `None` where the neighbouring
+/// chunk is not loaded.
+pub struct TileNeighbourhood {
+    tiles: [[Option<usize>; CHUNK_SIZE + 2]; CHUNK_SIZE + 2]
+}
+
+impl TileNeighbourhood {
+    /// Collect the tiles of `chunk`, tiles around it are looked up by their world tile
+    /// coordinate with `get_tile`.
+    pub fn new(
+        chunk: &Chunk,
+        get_tile: impl Fn(IVec2) -> Option<usize>
+    ) -> Self {
+        let mut tiles = [[None; CHUNK_SIZE + 2]; CHUNK_SIZE + 2];
+        let size = CHUNK_SIZE as i32;
+
+        for x in -1..=size {
+            for y in -1..=size {
+                tiles[(x + 1) as usize][(y + 1) as usize] = if (0..size).contains(&x) && (0..size).contains(&y) {
+                    Some(chunk.tiles[x as usize][y as usize].tile)
+                } else {
+                    get_tile(chunk.coordinate * size + IVec2::new(x, y))
+                };
+            }
+        }
+
+        TileNeighbourhood {
+            tiles
+        }
+    }
+
+    /// Tile type at a position relative to the chunk, from -1 to `CHUNK_SIZE` inclusive.
+    pub fn get(
+        &self,
+        x: i32,
+        y: i32
+    ) -> Option<usize> {
+        self.tiles[(x + 1) as usize][(y + 1) as usize]
+    }
+}
@@ -1,106 +1,448 @@
-use bevy::prelude::Resource;
-use crate::terrain::chunk::CHUNK_SIZE;
+use std::fmt;
+use bevy::prelude::{Color, Resource};
+use serde::{Deserialize, Serialize};
+use crate::terrain::autotile::Autotile;
+use crate::terrain::climate::ClimateTable;
+use crate::terrain::definition::{BiomeBlendDefinition, TerrainDefinition, TerrainDefinitionError, TileDefinition};
 use crate::terrain::foliage::{Foliage, FoliageType};
+use crate::terrain::noise::NoiseLayers;
+use crate::terrain::season::Season;
+use crate::weather::WeatherChain;
 
-#[derive(Resource)]
+/// Errors looking up or picking biomes, tiles and foliage while generating the world.
+///
+/// Biomes are checked when they are registered, so a registry built by `from_definition`
+/// only fails on lookups of biome types it does not contain.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorldGenError {
+    UnknownBiome { biome: BiomeType },
+    /// No biomes are registered to pick from.
+    NoBiomes,
+    /// A biome, or a tile or foliage entry of a biome, has a weight of zero.
+    ZeroWeight { biome: String, entry: Option<String> },
+    /// The weights of a biome's tiles or foliage, or of all biomes, add up to more than
+    /// `u16::MAX`.
+    WeightOverflow { biome: Option<String> },
+    /// A biome has no tiles to pick from, in its default tiles or those of a season.
+    EmptyTileTable { biome: String, season: Option<Season> },
+    /// A biome or foliage references a tile type that is not registered.
+    UnknownTile { referenced_by: String, tile: usize },
+    /// Noise or hash value outside of [0, 1].
+    RngOutOfRange { rng: f32 }
+}
+
+impl fmt::Display for WorldGenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorldGenError::UnknownBiome { biome } =>
+                write!(f, "Biome {} is not registered", biome.0),
+            WorldGenError::NoBiomes =>
+                write!(f, "No biomes are registered"),
+            WorldGenError::ZeroWeight { biome, entry: Some(entry) } =>
+                write!(f, "'{}' in biome '{}' has a weight of zero", entry, biome),
+            WorldGenError::ZeroWeight { biome, entry: None } =>
+                write!(f, "Biome '{}' has a weight of zero", biome),
+            WorldGenError::WeightOverflow { biome: Some(biome) } =>
+                write!(f, "Weights in biome '{}' add up to more than {}", biome, u16::MAX),
+            WorldGenError::WeightOverflow { biome: None } =>
+                write!(f, "Biome weights add up to more than {}", u16::MAX),
+            WorldGenError::EmptyTileTable { biome, season: Some(season) } =>
+                write!(f, "Biome '{}' has no tiles in {:?}", biome, season),
+            WorldGenError::EmptyTileTable { biome, season: None } =>
+                write!(f, "Biome '{}' has no tiles", biome),
+            WorldGenError::UnknownTile { referenced_by, tile } =>
+                write!(f, "'{}' references unregistered tile type {}", referenced_by, tile),
+            WorldGenError::RngOutOfRange { rng } =>
+                write!(f, "Random value {} is outside of [0, 1]", rng)
+        }
+    }
+}
+
+impl std::error::Error for WorldGenError {}
+
+/// Registry of the tiles, foliage and biomes the world is generated from.
+///
+/// Tiles, foliage and biomes are referred to by their index in the registry, the string
+/// ids from the definition file are only used to resolve references and to save chunks.
+#[derive(Resource, Clone)]
 pub struct BiomeHandle {
     pub biomes: Vec<Biome>,
-    pub biomes_weight_sum: u16
+    pub biomes_weight_sum: u16,
+    pub tiles: Vec<TileDefinition>,
+    /// Autotiling rules of each tile type, in the same order as `tiles`.
+    pub autotiles: Vec<Option<Autotile>>,
+    pub foliage: Vec<Foliage>,
+    /// Climate based biome lookup, biomes are picked by weight from a single noise value
+    /// when there is none.
+    pub climate: Option<ClimateTable>,
+    pub blend: Option<BiomeBlendDefinition>,
+    /// Noise layers tiles, foliage and biomes are picked with.
+    pub noise: NoiseLayers,
+    /// Season the biomes currently pick their tiles, foliage density and tint for.
+    pub season: Season
 }
 
 impl BiomeHandle {
     pub fn new() -> Self {
         BiomeHandle {
             biomes: Vec::new(),
-            biomes_weight_sum: 0
+            biomes_weight_sum: 0,
+            tiles: Vec::new(),
+            autotiles: Vec::new(),
+            foliage: Vec::new(),
+            climate: None,
+            blend: None,
+            noise: NoiseLayers::default(),
+            season: Season::default()
+        }
+    }
+
+    /// Build the registry from a terrain definition, resolving string ids to indices.
+    pub fn from_definition(
+        definition: &TerrainDefinition
+    ) -> Result<Self, TerrainDefinitionError> {
+        definition.validate()?;
+
+        let mut biome_handle = BiomeHandle::new();
+        biome_handle.tiles = definition.tiles.clone();
+        biome_handle.blend = definition.blend;
+        biome_handle.noise = NoiseLayers::from(&definition.noise);
+
+        biome_handle.autotiles = definition.tiles.iter()
+            .map(|tile| tile.autotile.as_ref().map(|autotile| Autotile {
+                mode: autotile.mode,
+                atlas_start: autotile.atlas_start,
+                connects_to: autotile.connects_to.iter()
+                    .filter_map(|id| biome_handle.tile_id(id))
+                    .collect()
+            }))
+            .collect();
+
+        for (index, foliage) in definition.foliage.iter().enumerate() {
+            let spawns_on = foliage.spawns_on.iter()
+                .filter_map(|tile| biome_handle.tile_id(tile))
+                .collect();
+
+            biome_handle.foliage.push(Foliage {
+                id: foliage.id.clone(),
+                foliage_type: FoliageType(index as u16),
+                weight: foliage.weight,
+                scale: foliage.scale,
+                sprite: foliage.sprite.clone(),
+                spawns_on
+            });
+        }
+
+        for (index, biome_definition) in definition.biomes.iter().enumerate() {
+            let mut biome = Biome::new(
+                &biome_definition.id,
+                biome_definition.weight,
+                BiomeType(index as u16),
+                biome_definition.foliage_density);
+
+            if let Some([r, g, b]) = biome_definition.tint {
+                biome.tint = Color::rgb(r, g, b);
+            }
+
+            for tile in &biome_definition.tiles {
+                if let Some(tile_type) = biome_handle.tile_id(&tile.tile) {
+                    biome = biome.add_tile(TileType {
+                        tile_type,
+                        weight: tile.weight
+                    });
+                }
+            }
+
+            biome.weather = WeatherChain::from_definition(&biome_definition.weather);
+
+            biome.seasons = Season::ALL.iter()
+                .map(|season| {
+                    let mut biome_season = BiomeSeason {
+                        tiles: biome.tiles.clone(),
+                        tiles_weight_sum: biome.tiles_weight_sum,
+                        foliage_density: biome.foliage_density,
+                        tint: biome.tint
+                    };
+
+                    let overrides = biome_definition.seasons.iter()
+                        .filter(|season_definition| season_definition.season == *season);
+
+                    for season_definition in overrides {
+                        if let Some(tiles) = &season_definition.tiles {
+                            biome_season.tiles = tiles.iter()
+                                .filter_map(|tile| Some(TileType {
+                                    tile_type: biome_handle.tile_id(&tile.tile)?,
+                                    weight: tile.weight
+                                }))
+                                .collect();
+                            biome_season.tiles_weight_sum = biome_season.tiles.iter()
+                                .fold(0, |sum, tile| sum.saturating_add(tile.weight));
+                        }
+
+                        if let Some(foliage_density) = season_definition.foliage_density {
+                            biome_season.foliage_density = foliage_density;
+                        }
+
+                        if let Some([r, g, b]) = season_definition.tint {
+                            biome_season.tint = Color::rgb(r, g, b);
+                        }
+                    }
+
+                    biome_season
+                })
+                .collect();
+
+            for foliage_id in &biome_definition.foliage {
+                if let Some(foliage) = biome_handle.foliage_by_id(foliage_id) {
+                    biome = biome.add_foliage(foliage.clone());
+                }
+            }
+
+            biome_handle.add_biome(biome)?;
         }
+
+        if let Some(climate) = &definition.climate {
+            let biome_id = |id: &str| biome_handle.biome_id(id).unwrap_or_default();
+
+            biome_handle.climate = Some(ClimateTable {
+                sea_level: climate.sea_level,
+                beach_level: climate.beach_level,
+                ocean: biome_id(&climate.ocean),
+                beach: biome_id(&climate.beach),
+                temperature_bands: climate.temperature_bands.clone(),
+                moisture_bands: climate.moisture_bands.clone(),
+                table: climate.table.iter()
+                    .map(|row| row.iter().map(|id| biome_id(id)).collect())
+                    .collect()
+            });
+        }
+
+        biome_handle.set_season(Season::default());
+
+        Ok(biome_handle)
     }
 
+    /// Switch every biome to the tiles, foliage density and tint of a season.
+    pub fn set_season(
+        &mut self,
+        season: Season
+    ) {
+        self.season = season;
+
+        for biome in &mut self.biomes {
+            biome.set_season(season);
+        }
+    }
+
+    /// Copy of the registry switched to a season.
+    pub fn for_season(
+        &self,
+        season: Season
+    ) -> Self {
+        let mut biome_handle = self.clone();
+        biome_handle.set_season(season);
+        biome_handle
+    }
+
+    /// Whether any biomes have been registered yet.
+    pub fn is_loaded(&self) -> bool {
+        !self.biomes.is_empty()
+    }
+
+    /// Register a biome after checking its weights are non-zero, it has tiles in every
+    /// season and its tiles and foliage only reference registered tiles.
     pub fn add_biome(
         &mut self,
         biome: Biome
-    ) {
-        self.biomes.push(biome.clone());
-        self.biomes_weight_sum += biome.weight;
+    ) -> Result<(), WorldGenError> {
+        self.validate_biome(&biome)?;
+
+        self.biomes_weight_sum = self.biomes_weight_sum.checked_add(biome.weight)
+            .ok_or(WorldGenError::WeightOverflow { biome: None })?;
+        self.biomes.push(biome);
+
+        Ok(())
     }
 
-    /// Retrieve biome in biome handle of biome type.
-    ///
-    /// panics when biome is not found
-    pub fn get_biome(
+    fn validate_biome(
         &self,
-        biome_type: BiomeType
-    ) -> &Biome {
-        for biome in &self.biomes {
-            if biome.biome_type == biome_type {
-                return biome;
+        biome: &Biome
+    ) -> Result<(), WorldGenError> {
+        let zero_weight = |entry: Option<&str>| WorldGenError::ZeroWeight {
+            biome: biome.id.clone(),
+            entry: entry.map(str::to_string)
+        };
+        let overflow = || WorldGenError::WeightOverflow { biome: Some(biome.id.clone()) };
+
+        if biome.weight == 0 {
+            return Err(zero_weight(None));
+        }
+
+        let tile_tables = std::iter::once((None, &biome.tiles))
+            .chain(biome.seasons.iter().enumerate()
+                .map(|(season, biome_season)| (Some(Season::ALL[season]), &biome_season.tiles)));
+
+        for (season, tiles) in tile_tables {
+            if tiles.is_empty() {
+                return Err(WorldGenError::EmptyTileTable { biome: biome.id.clone(), season });
+            }
+
+            for tile in tiles {
+                let Some(definition) = self.tiles.get(tile.tile_type) else {
+                    return Err(WorldGenError::UnknownTile { referenced_by: biome.id.clone(), tile: tile.tile_type });
+                };
+
+                if tile.weight == 0 {
+                    return Err(zero_weight(Some(&definition.id)));
+                }
             }
+
+            weight_sum(tiles.iter().map(|tile| tile.weight)).ok_or_else(overflow)?;
         }
 
-        // Get biome should never be called when the biome does not exist.
-        panic!("Biome not found");
+        for foliage in &biome.foliage {
+            if foliage.weight == 0 {
+                return Err(zero_weight(Some(&foliage.id)));
+            }
+
+            if let Some(tile) = foliage.spawns_on.iter().find(|tile| **tile >= self.tiles.len()) {
+                return Err(WorldGenError::UnknownTile { referenced_by: foliage.id.clone(), tile: *tile });
+            }
+        }
+
+        weight_sum(biome.foliage.iter().map(|foliage| foliage.weight)).ok_or_else(overflow)?;
+
+        Ok(())
+    }
+
+    pub fn tile_id(
+        &self,
+        id: &str
+    ) -> Option<usize> {
+        self.tiles.iter().position(|tile| tile.id == id)
+    }
+
+    pub fn biome_id(
+        &self,
+        id: &str
+    ) -> Option<BiomeType> {
+        self.biomes.iter()
+            .find(|biome| biome.id == id)
+            .map(|biome| biome.biome_type)
+    }
+
+    pub fn foliage_id(
+        &self,
+        id: &str
+    ) -> Option<FoliageType> {
+        self.foliage_by_id(id).map(|foliage| foliage.foliage_type)
+    }
+
+    fn foliage_by_id(
+        &self,
+        id: &str
+    ) -> Option<&Foliage> {
+        self.foliage.iter().find(|foliage| foliage.id == id)
+    }
+
+    pub fn get_autotile(
+        &self,
+        tile_type: usize
+    ) -> Option<&Autotile> {
+        self.autotiles.get(tile_type)?.as_ref()
     }
 
+    pub fn get_foliage(
+        &self,
+        foliage_type: FoliageType
+    ) -> Option<&Foliage> {
+        self.foliage.get(foliage_type.0 as usize)
+    }
+
+    /// Retrieve biome in biome handle of biome type.
+    pub fn get_biome(
+        &self,
+        biome_type: BiomeType
+    ) -> Result<&Biome, WorldGenError> {
+        self.biomes.iter()
+            .find(|biome| biome.biome_type == biome_type)
+            .ok_or(WorldGenError::UnknownBiome { biome: biome_type })
+    }
+
+    /// Pick a biome by weight from a value in [0, 1].
     pub fn get_biome_from_rng(
         &self,
         rng: f32
-    ) -> BiomeType {
-        assert!(rng < 1.0 && rng >= 0.0);
+    ) -> Result<BiomeType, WorldGenError> {
+        if self.biomes_weight_sum == 0 {
+            return Err(WorldGenError::NoBiomes);
+        }
 
+        let val = weighted_value(rng, self.biomes_weight_sum)?;
         let mut sum: u16 = 0;
-        let val: u16 = (rng * self.biomes_weight_sum as f32) as u16;
 
         for biome in &self.biomes {
             sum += biome.weight;
             if sum > val {
-                return biome.biome_type;
+                return Ok(biome.biome_type);
             }
         }
 
-        panic!("Tile weight calculation error.")
+        Err(WorldGenError::NoBiomes)
     }
+}
 
-    pub fn get_biome_type_array_from_rng(
-        &self,
-        rng_array: [[f32; CHUNK_SIZE]; CHUNK_SIZE]
-    ) -> [[BiomeType; CHUNK_SIZE]; CHUNK_SIZE] {
-        let mut biome_type_array = [[BiomeType::PLAINS; CHUNK_SIZE]; CHUNK_SIZE];
-
-        for x in 0..CHUNK_SIZE {
-            for y in 0..CHUNK_SIZE {
-                biome_type_array[x][y] = self.get_biome_from_rng(rng_array[x][y]);
-            }
-        }
-
-        biome_type_array
+impl Default for BiomeHandle {
+    fn default() -> Self {
+        BiomeHandle::new()
     }
 }
 
 #[derive(Clone)]
 pub struct Biome {
+    pub id: String,
     tiles: Vec<TileType>,
     foliage: Vec<Foliage>,
     pub weight: u16,
     pub biome_type: BiomeType,
     pub foliage_density: f32,
+    /// Colour of the tinted tiles in the biome.
+    pub tint: Color,
     tiles_weight_sum: u16,
-    foliage_weight_sum: u16
+    foliage_weight_sum: u16,
+    /// Tiles, foliage density and tint of each season in `Season::ALL` order, empty when
+    /// the biome does not change with the seasons.
+    seasons: Vec<BiomeSeason>,
+    pub weather: WeatherChain
+}
+
+#[derive(Clone)]
+struct BiomeSeason {
+    tiles: Vec<TileType>,
+    tiles_weight_sum: u16,
+    foliage_density: f32,
+    tint: Color
 }
 
 impl Biome {
     pub fn new(
+        id: &str,
         weight: u16,
         biome_type: BiomeType,
         foliage_density: f32
     ) -> Self {
         Biome {
+            id: id.to_string(),
             tiles: Vec::new(),
             foliage: Vec::new(),
             weight,
             biome_type,
             foliage_density,
+            tint: Color::WHITE,
             tiles_weight_sum: 0,
-            foliage_weight_sum: 0
+            foliage_weight_sum: 0,
+            seasons: Vec::new(),
+            weather: WeatherChain::default()
         }
     }
 
@@ -108,8 +450,8 @@ impl Biome {
         mut self,
         tile: TileType
     ) -> Self {
-        self.tiles.push(tile.clone());
-        self.tiles_weight_sum += tile.weight.clone();
+        self.tiles_weight_sum = self.tiles_weight_sum.saturating_add(tile.weight);
+        self.tiles.push(tile);
         self
     }
 
@@ -117,79 +459,110 @@ impl Biome {
         mut self,
         foliage: Foliage
     ) -> Self {
-        self.foliage.push(foliage.clone());
-        self.foliage_weight_sum += foliage.weight;
+        self.foliage_weight_sum = self.foliage_weight_sum.saturating_add(foliage.weight);
+        self.foliage.push(foliage);
         self
     }
 
+    fn set_season(
+        &mut self,
+        season: Season
+    ) {
+        if let Some(biome_season) = self.seasons.get(season as usize) {
+            self.tiles = biome_season.tiles.clone();
+            self.tiles_weight_sum = biome_season.tiles_weight_sum;
+            self.foliage_density = biome_season.foliage_density;
+            self.tint = biome_season.tint;
+        }
+    }
+
+    /// Pick a tile type by weight from a value in [0, 1].
     pub fn get_tile_from_rng(
         &self,
         rng: f32
-    ) -> usize {
-        assert!(rng < 1.0 && rng >= 0.0);
+    ) -> Result<usize, WorldGenError> {
+        let empty = || WorldGenError::EmptyTileTable { biome: self.id.clone(), season: None };
 
+        if self.tiles_weight_sum == 0 {
+            return Err(empty());
+        }
+
+        let val = weighted_value(rng, self.tiles_weight_sum)?;
         let mut sum: u16 = 0;
-        let val: u16 = (rng * self.tiles_weight_sum as f32) as u16;
 
         for tile in &self.tiles {
             sum += tile.weight;
             if sum > val {
-                return tile.tile_type;
+                return Ok(tile.tile_type);
             }
         }
 
-        panic!("Tile weight calculation error.")
+        Err(empty())
     }
 
+    /// Pick the foliage growing on a tile from a value in [0, 1], `FoliageType::NONE` when
+    /// the value is above the foliage density or the picked foliage can't grow on the tile.
     pub fn get_foliage_from_rng(
         &self,
         rng: f32,
         tile_type: usize
-    ) -> FoliageType {
-        assert!(rng < 1.0 && rng >= 0.0);
-
-        if self.foliage_weight_sum > 0 {
-            if rng < self.foliage_density {
-                let mut sum: u16 = 0;
-                let val: u16 = (rng * self.foliage_weight_sum as f32) as u16;
-
-                for foliage in &self.foliage {
-                    sum += foliage.weight;
-                    if sum > val {
-                        if foliage.spawns_on.contains(&tile_type) {
-                            return foliage.foliage_type;
-                        }
-                    }
-                }
+    ) -> Result<FoliageType, WorldGenError> {
+        if self.foliage_weight_sum == 0 || rng >= self.foliage_density {
+            // Still reject invalid values so they are not silently treated as no foliage
+            weighted_value(rng, 1)?;
+            return Ok(FoliageType::NONE);
+        }
+
+        let val = weighted_value(rng, self.foliage_weight_sum)?;
+        let mut sum: u16 = 0;
+
+        for foliage in &self.foliage {
+            sum += foliage.weight;
+            if sum > val && foliage.spawns_on.contains(&tile_type) {
+                return Ok(foliage.foliage_type);
             }
         }
 
-        FoliageType::NONE
+        Ok(FoliageType::NONE)
+    }
+
+    /// Whether the foliage is part of this biome and can grow on the tile type.
+    pub fn foliage_spawns_on(
+        &self,
+        foliage_type: FoliageType,
+        tile_type: usize
+    ) -> bool {
+        if foliage_type == FoliageType::NONE {
+            return true;
+        }
+
+        self.foliage.iter().any(|foliage| {
+            foliage.foliage_type == foliage_type && foliage.spawns_on.contains(&tile_type)
+        })
     }
 }
 
 impl Default for Biome {
     fn default() -> Self {
         Biome {
+            id: String::new(),
             tiles: Vec::new(),
             foliage: Vec::new(),
-            biome_type: BiomeType::PLAINS,
+            biome_type: BiomeType::default(),
             weight: 0,
             foliage_density: 0.0,
+            tint: Color::WHITE,
             tiles_weight_sum: 0,
-            foliage_weight_sum: 0
+            foliage_weight_sum: 0,
+            seasons: Vec::new(),
+            weather: WeatherChain::default()
         }
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
-pub enum BiomeType {
-    PLAINS,
-    DESERT,
-    GRASSLAND,
-    BEACH,
-    OCEAN
-}
+/// Index of a biome in the `BiomeHandle`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+pub struct BiomeType(pub u16);
 
 #[derive(Clone)]
 pub struct TileType {
@@ -198,12 +571,33 @@ pub struct TileType {
 }
 
 impl TileType {
-    pub fn set_weight(
-        mut self,
+    /// Entry of a biome's tile table picking the tile at `tile_type` in the `BiomeHandle`.
+    pub fn new(
+        tile_type: usize,
         weight: u16
-    ) -> TileType {
-        self.weight = weight;
+    ) -> Self {
+        TileType {
+            tile_type,
+            weight
+        }
+    }
+}
 
-        self
+/// Scale a value in [0, 1] to a weight in [0, `weight_sum`), exactly 1 picks the last weight.
+fn weighted_value(
+    rng: f32,
+    weight_sum: u16
+) -> Result<u16, WorldGenError> {
+    if !(0.0..=1.0).contains(&rng) {
+        return Err(WorldGenError::RngOutOfRange { rng });
     }
-}
\ No newline at end of file
+
+    Ok(((rng * weight_sum as f32) as u16).min(weight_sum.saturating_sub(1)))
+}
+
+/// Sum of weights, `None` when it does not fit the `u16` weight sums.
+fn weight_sum(
+    mut weights: impl Iterator<Item = u16>
+) -> Option<u16> {
+    weights.try_fold(0u16, |sum, weight| sum.checked_add(weight))
+}
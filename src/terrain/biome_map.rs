@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+use crate::terrain::biome::{BiomeHandle, BiomeType, WorldGenError};
+use crate::terrain::chunk::CHUNK_SIZE;
+use crate::terrain::definition::{BiomeBlendDefinition, BiomeBlendMode};
+use crate::terrain::noise::{NoiseSampler, NoiseSettings, position_hash};
+use crate::terrain::seed::WorldSeed;
+
+/// Default noise used to pick biomes by weight when there is no climate table.
+pub const BIOME_NOISE: NoiseSettings = NoiseSettings::new(0.1, 3);
+
+/// 4x4 ordered dithering thresholds.
+const BAYER_MATRIX: [[u8; 4]; 4] = [
+    [0, 8, 2, 10],
+    [12, 4, 14, 6],
+    [3, 11, 1, 9],
+    [15, 7, 13, 5]
+];
+
+/// Samples the biome at any point of the world.
+struct BiomeSampler<'a> {
+    biome_handle: &'a BiomeHandle,
+    biome: NoiseSampler<'a>,
+    temperature: NoiseSampler<'a>,
+    moisture: NoiseSampler<'a>,
+    elevation: NoiseSampler<'a>
+}
+
+impl<'a> BiomeSampler<'a> {
+    fn new(
+        seed: &WorldSeed,
+        biome_handle: &'a BiomeHandle
+    ) -> Self {
+        BiomeSampler {
+            biome_handle,
+            biome: biome_handle.noise.biome.sampler(seed.biome()),
+            temperature: biome_handle.noise.temperature.sampler(seed.temperature()),
+            moisture: biome_handle.noise.moisture.sampler(seed.moisture()),
+            elevation: biome_handle.noise.elevation.sampler(seed.elevation())
+        }
+    }
+
+    /// Biome at a point given in chunk units.
+    fn biome_at(
+        &self,
+        point: Vec2
+    ) -> Result<BiomeType, WorldGenError> {
+        match &self.biome_handle.climate {
+            Some(climate) => {
+                let temperature = self.temperature.sample(point);
+                let moisture = self.moisture.sample(point);
+                let elevation = self.elevation.sample(point);
+
+                Ok(climate.get_biome(temperature, moisture, elevation))
+            }
+            None => {
+                self.biome_handle.get_biome_from_rng(self.biome.sample(point))
+            }
+        }
+    }
+}
+
+/// Biomes of a chunk, plus a border of the blend width around it so tiles near the chunk
+/// edge can blend with biomes in neighbouring chunks.
+pub struct BiomeMap {
+    coordinate: IVec2,
+    blend: Option<BiomeBlendDefinition>,
+    border: i32,
+    side: i32,
+    biomes: Vec<BiomeType>
+}
+
+impl BiomeMap {
+    pub fn generate(
+        coordinate: IVec2,
+        seed: &WorldSeed,
+        biome_handle: &BiomeHandle
+    ) -> Result<Self, WorldGenError> {
+        let sampler = BiomeSampler::new(seed, biome_handle);
+
+        let blend = biome_handle.blend.filter(|blend| blend.width > 0);
+        let border = blend.map(|blend| blend.width as i32).unwrap_or(0);
+        let side = CHUNK_SIZE as i32 + 2 * border;
+
+        let mut biomes = Vec::with_capacity((side * side) as usize);
+
+        for x in -border..(CHUNK_SIZE as i32 + border) {
+            for y in -border..(CHUNK_SIZE as i32 + border) {
+                let point = Vec2::new(x as f32, y as f32) / CHUNK_SIZE as f32 + coordinate.as_vec2();
+                biomes.push(sampler.biome_at(point)?);
+            }
+        }
+
+        Ok(BiomeMap {
+            coordinate,
+            blend,
+            border,
+            side,
+            biomes
+        })
+    }
+
+    /// Biome at a tile position relative to the chunk, within the border.
+    pub fn get(
+        &self,
+        x: i32,
+        y: i32
+    ) -> BiomeType {
+        self.biomes[((x + self.border) * self.side + y + self.border) as usize]
+    }
+
+    /// Biome the tile at `x`, `y` picks its tile from.
+    ///
+    /// Without blending this is the biome at the tile. With blending, nearby biomes are
+    /// weighted by their distance to the tile and one is picked using a dither pattern or
+    /// white noise, so borders between biomes break up instead of following tile edges.
+    pub fn blended_biome(
+        &self,
+        x: usize,
+        y: usize,
+        seed: &WorldSeed
+    ) -> BiomeType {
+        let (x, y) = (x as i32, y as i32);
+
+        let Some(blend) = self.blend else {
+            return self.get(x, y);
+        };
+
+        let width = blend.width as i32;
+        let mut weights: Vec<(BiomeType, i32)> = Vec::new();
+
+        for dx in -width..=width {
+            for dy in -width..=width {
+                let biome = self.get(x + dx, y + dy);
+                let weight = width + 1 - dx.abs().max(dy.abs());
+
+                match weights.iter_mut().find(|(weighted_biome, _)| *weighted_biome == biome) {
+                    Some((_, biome_weight)) => *biome_weight += weight,
+                    None => weights.push((biome, weight))
+                }
+            }
+        }
+
+        if weights.len() == 1 {
+            return weights[0].0;
+        }
+
+        let world_position = self.coordinate * CHUNK_SIZE as i32 + IVec2::new(x, y);
+        let threshold = match blend.mode {
+            BiomeBlendMode::Dither => {
+                let (cell_x, cell_y) = (world_position.x.rem_euclid(4), world_position.y.rem_euclid(4));
+                (BAYER_MATRIX[cell_y as usize][cell_x as usize] as f32 + 0.5) / 16.0
+            }
+            BiomeBlendMode::Noise => position_hash(seed.blend(), world_position)
+        };
+
+        let total: i32 = weights.iter().map(|(_, weight)| weight).sum();
+        let mut target = threshold * total as f32;
+
+        for (biome, weight) in &weights {
+            if target < *weight as f32 {
+                return *biome;
+            }
+
+            target -= *weight as f32;
+        }
+
+        weights[weights.len() - 1].0
+    }
+}
@@ -1,64 +1,133 @@
 use bevy::{
     prelude::*,
 };
-use crate::terrain::biome::{BiomeHandle, BiomeType};
+use crate::terrain::atlas::TileAtlas;
+use crate::terrain::autotile::TileNeighbourhood;
+use crate::terrain::biome::{BiomeHandle, BiomeType, WorldGenError};
 use crate::terrain::foliage::FoliageType;
-use crate::terrain::meshing::ChunkTileMapBuilder;
-use crate::terrain::noise::get_noise;
-use crate::terrain::tile::Tile;
+use crate::terrain::meshing::{ChunkTileMapBuilder, greedy_quads, MeshingMode, TileAppearance, TileVariants};
+use crate::terrain::generator::{ChunkData, foliage_noise, terrain_noise};
+use crate::terrain::region::ChunkSave;
+use crate::terrain::season::{Season, SeasonalUpdate};
+use crate::terrain::seed::WorldSeed;
+use crate::terrain::tile::{Tile, TileChanged};
 
 pub const CHUNK_SIZE: usize = 16;
 
 pub const TILE_SIZE: f32 = 1.0;
 pub const CHUNK_SIDE_SIZE: f32 = TILE_SIZE * CHUNK_SIZE as f32;
 
+/// Offsets of the 8 neighbours of a tile or chunk, clockwise starting north.
+pub const NEIGHBOUR_OFFSETS: [IVec2; 8] = [
+    IVec2::new(0, 1),
+    IVec2::new(1, 1),
+    IVec2::new(1, 0),
+    IVec2::new(1, -1),
+    IVec2::new(0, -1),
+    IVec2::new(-1, -1),
+    IVec2::new(-1, 0),
+    IVec2::new(-1, 1)
+];
+
+/// Chunk coordinate containing the world position.
+pub fn world_to_chunk_coordinate(
+    position: Vec2
+) -> IVec2 {
+    (position / CHUNK_SIDE_SIZE).floor().as_ivec2()
+}
+
+/// World tile coordinate of the tile containing the world position.
+pub fn world_to_tile_coordinate(
+    position: Vec2
+) -> IVec2 {
+    (position / TILE_SIZE).floor().as_ivec2()
+}
+
+/// Chunk coordinate of a world tile coordinate, along with the tile position inside the chunk.
+pub fn tile_to_chunk_coordinate(
+    tile_coordinate: IVec2
+) -> (IVec2, usize, usize) {
+    let size = CHUNK_SIZE as i32;
+
+    (
+        IVec2::new(tile_coordinate.x.div_euclid(size), tile_coordinate.y.div_euclid(size)),
+        tile_coordinate.x.rem_euclid(size) as usize,
+        tile_coordinate.y.rem_euclid(size) as usize
+    )
+}
+
+/// World tile coordinate of the tile at `x`, `y` inside a chunk.
+pub fn chunk_to_tile_coordinate(
+    chunk_coordinate: IVec2,
+    x: usize,
+    y: usize
+) -> IVec2 {
+    chunk_coordinate * CHUNK_SIZE as i32 + IVec2::new(x as i32, y as i32)
+}
+
 #[derive(Component)]
 pub struct Chunk {
     pub tiles: [[Tile; CHUNK_SIZE]; CHUNK_SIZE],
     pub foliage_type: [[FoliageType; CHUNK_SIZE]; CHUNK_SIZE],
-    pub coordinate: Vec2,
-    chunk_tile_map_builder: ChunkTileMapBuilder,
+    pub coordinate: IVec2,
+    /// Set when the chunk has changes that have not been written to its region file yet.
+    pub modified: bool,
+    /// Season the tiles were generated or last updated for, `None` for chunks loaded from
+    /// a save.
+    pub season: Option<Season>
 }
 
 #[derive(Component)]
 pub struct ChunkCoordinate {
-    pub coordinate: Vec2,
+    #[allow(dead_code)]
+    pub coordinate: IVec2,
 }
 
 impl Chunk {
-    pub fn new(
-        coordinate: Vec2,
-        seed: u32,
-        biome_handle: &ResMut<BiomeHandle>
+    pub fn from_data(
+        data: ChunkData
     ) -> Self {
-        // Init blocks for chunk mesh layer
-        let mut tiles: [[Tile; CHUNK_SIZE]; CHUNK_SIZE] = [[Tile::default(); CHUNK_SIZE]; CHUNK_SIZE];
-
-        // Get noise map for terrain type
-        let noise = get_noise(coordinate, seed, 0.7, 5);
+        Chunk {
+            tiles: data.tiles,
+            foliage_type: data.foliage_type,
+            coordinate: data.coordinate,
+            modified: false,
+            season: Some(data.season)
+        }
+    }
 
-        // Get noise map for biome type
-        let biome_noise = get_noise(coordinate, seed, 0.1, 3);
-        let biome_type: [[BiomeType; CHUNK_SIZE]; CHUNK_SIZE] = biome_handle.get_biome_type_array_from_rng(biome_noise);
+    /// Restore a previously saved chunk, mapping the saved string ids to the current
+    /// biome handle. Unknown tiles and biomes fall back to the first definition and unknown
+    /// foliage is dropped.
+    pub fn from_save(
+        coordinate: IVec2,
+        chunk_save: ChunkSave,
+        biome_handle: &BiomeHandle
+    ) -> Self {
+        let tile_map: Vec<usize> = chunk_save.tile_ids.iter()
+            .map(|id| biome_handle.tile_id(id).unwrap_or_default())
+            .collect();
+        let biome_map: Vec<BiomeType> = chunk_save.biome_ids.iter()
+            .map(|id| biome_handle.biome_id(id).unwrap_or_default())
+            .collect();
+        let foliage_map: Vec<FoliageType> = chunk_save.foliage_ids.iter()
+            .map(|id| biome_handle.foliage_id(id).unwrap_or(FoliageType::NONE))
+            .collect();
 
-        // Foliage array
-        let foliage_noise = get_noise(coordinate, seed, 5.0, 5);
-        let mut foliage_type: [[FoliageType; CHUNK_SIZE]; CHUNK_SIZE] = [[FoliageType::NONE; CHUNK_SIZE]; CHUNK_SIZE];
+        let mut tiles = chunk_save.tiles;
+        let mut foliage_type = chunk_save.foliage_type;
 
-        // Iterate over each tile in chunk
         for x in 0..CHUNK_SIZE {
             for y in 0..CHUNK_SIZE {
-                let biome = biome_handle.get_biome(biome_type[x][y]);
-                let tile_type = biome.get_tile_from_rng(noise[x][y]);
+                let tile = &mut tiles[x][y];
+                tile.tile = tile_map.get(tile.tile).copied().unwrap_or_default();
+                tile.biome = biome_map.get(tile.biome.0 as usize).copied().unwrap_or_default();
 
-                // Set tile
-                tiles[x][y] = Tile {
-                    tile: tile_type,
-                    biome: biome_type[x][y].clone()
-                };
-
-                // Set foliage
-                foliage_type[x][y] = biome.get_foliage_from_rng(foliage_noise[x][y], tile_type);
+                if foliage_type[x][y] != FoliageType::NONE {
+                    foliage_type[x][y] = foliage_map.get(foliage_type[x][y].0 as usize)
+                        .copied()
+                        .unwrap_or(FoliageType::NONE);
+                }
             }
         }
 
@@ -66,45 +135,278 @@ impl Chunk {
             tiles,
             foliage_type,
             coordinate,
-            chunk_tile_map_builder: ChunkTileMapBuilder::default()
+            modified: false,
+            season: None
         }
     }
 
-    #[allow(dead_code)]
-    pub fn set_block(
+    pub fn to_save(
+        &self,
+        biome_handle: &BiomeHandle
+    ) -> ChunkSave {
+        ChunkSave {
+            tile_ids: biome_handle.tiles.iter().map(|tile| tile.id.clone()).collect(),
+            biome_ids: biome_handle.biomes.iter().map(|biome| biome.id.clone()).collect(),
+            foliage_ids: biome_handle.foliage.iter().map(|foliage| foliage.id.clone()).collect(),
+            tiles: self.tiles,
+            foliage_type: self.foliage_type
+        }
+    }
+
+    /// Change the tile type, keeping the tile's biome. Foliage that can no longer grow on
+    /// the new tile is removed.
+    ///
+    /// Returns the change, or `None` when the tile already has that type.
+    pub fn set_tile(
         &mut self,
         x: usize,
         y: usize,
-        block: usize
-    ) {
-        self.tiles[x][y] = Tile {
-            tile: block,
-            biome: BiomeType::PLAINS
+        tile_type: usize,
+        biome_handle: &BiomeHandle
+    ) -> Option<TileChanged> {
+        let old_tile = self.tiles[x][y];
+        if old_tile.tile == tile_type {
+            return None;
+        }
+
+        let new_tile = Tile {
+            tile: tile_type,
+            biome: old_tile.biome
+        };
+
+        let old_foliage = self.foliage_type[x][y];
+        let new_foliage = if biome_handle.get_biome(new_tile.biome).is_ok_and(|biome| biome.foliage_spawns_on(old_foliage, tile_type)) {
+            old_foliage
+        } else {
+            FoliageType::NONE
         };
+
+        Some(self.apply_change(x, y, new_tile, new_foliage))
     }
 
-    pub fn generate_mesh (
+    /// Change the foliage on a tile, failing when the foliage cannot grow on the tile.
+    ///
+    /// Returns the change, or `None` when nothing changed or the foliage is not valid.
+    pub fn set_foliage(
         &mut self,
-    ) -> Mesh {
+        x: usize,
+        y: usize,
+        foliage_type: FoliageType,
+        biome_handle: &BiomeHandle
+    ) -> Option<TileChanged> {
+        let tile = self.tiles[x][y];
+        if self.foliage_type[x][y] == foliage_type
+            || !biome_handle.get_biome(tile.biome).is_ok_and(|biome| biome.foliage_spawns_on(foliage_type, tile.tile)) {
+            return None;
+        }
+
+        Some(self.apply_change(x, y, tile, foliage_type))
+    }
+
+    fn apply_change(
+        &mut self,
+        x: usize,
+        y: usize,
+        new_tile: Tile,
+        new_foliage: FoliageType
+    ) -> TileChanged {
+        let change = TileChanged {
+            chunk_coordinate: self.coordinate,
+            x,
+            y,
+            old_tile: self.tiles[x][y],
+            new_tile,
+            old_foliage: self.foliage_type[x][y],
+            new_foliage
+        };
+
+        self.tiles[x][y] = new_tile;
+        self.foliage_type[x][y] = new_foliage;
+        self.modified = true;
+
+        change
+    }
+
+    /// Changes bringing the tiles and foliage of a chunk to `season`, `season_handles` holds
+    /// the biome handle of each season in `Season::ALL` order.
+    ///
+    /// Only tiles and foliage that one of the seasons would have generated change, so tiles
+    /// placed by the player are kept.
+    pub fn seasonal_update(
+        coordinate: IVec2,
+        tiles: [[Tile; CHUNK_SIZE]; CHUNK_SIZE],
+        foliage_type: [[FoliageType; CHUNK_SIZE]; CHUNK_SIZE],
+        chunk_season: Option<Season>,
+        season: Season,
+        seed: &WorldSeed,
+        season_handles: &[BiomeHandle]
+    ) -> Result<SeasonalUpdate, WorldGenError> {
+        let new_handle = &season_handles[season as usize];
+        let noise = terrain_noise(coordinate, seed, new_handle);
+        let foliage_noise = foliage_noise(coordinate, seed, new_handle);
+        let old_handle = chunk_season.map(|chunk_season| &season_handles[chunk_season as usize]);
+
+        let mut changes = Vec::new();
+        let mut tint_changed = old_handle.is_none();
+
         for x in 0..CHUNK_SIZE {
             for y in 0..CHUNK_SIZE {
-                self.chunk_tile_map_builder.add_tile(
-                    Vec2::new(x as f32 * TILE_SIZE, y as f32 * TILE_SIZE),
-                    self.tiles[x][y].tile);
+                let old_tile = tiles[x][y];
+                let old_foliage = foliage_type[x][y];
+                let biome_type = old_tile.biome;
+
+                // What each season generates here
+                let mut natural_tile = false;
+                let mut natural_foliage = false;
+
+                for biome_handle in season_handles {
+                    let biome = biome_handle.get_biome(biome_type)?;
+                    let tile_type = biome.get_tile_from_rng(noise[x][y])?;
+                    let foliage = biome.get_foliage_from_rng(foliage_noise[x][y], tile_type)?;
+
+                    natural_tile |= tile_type == old_tile.tile;
+                    natural_foliage |= (tile_type, foliage) == (old_tile.tile, old_foliage);
+                }
+
+                let new_biome = new_handle.get_biome(biome_type)?;
+                let new_tile_type = if natural_tile {
+                    new_biome.get_tile_from_rng(noise[x][y])?
+                } else {
+                    old_tile.tile
+                };
+
+                let new_foliage = if natural_foliage {
+                    new_biome.get_foliage_from_rng(foliage_noise[x][y], new_tile_type)?
+                } else if new_biome.foliage_spawns_on(old_foliage, new_tile_type) {
+                    old_foliage
+                } else {
+                    FoliageType::NONE
+                };
+
+                if new_tile_type != old_tile.tile || new_foliage != old_foliage {
+                    changes.push(TileChanged {
+                        chunk_coordinate: coordinate,
+                        x,
+                        y,
+                        old_tile,
+                        new_tile: Tile {
+                            tile: new_tile_type,
+                            biome: biome_type
+                        },
+                        old_foliage,
+                        new_foliage
+                    });
+                }
+
+                let tinted = new_handle.tiles.get(new_tile_type).is_some_and(|tile| tile.tinted);
+                if let (true, Some(old_handle)) = (tinted, old_handle) {
+                    tint_changed |= old_handle.get_biome(biome_type)?.tint != new_biome.tint;
+                }
             }
         }
 
-        let mesh = self.chunk_tile_map_builder.build();
+        Ok(SeasonalUpdate {
+            season,
+            changes,
+            tint_changed
+        })
+    }
+
+    /// Apply a change from `seasonal_update` without marking the chunk as modified, seasons
+    /// are reapplied whenever the chunk is generated.
+    ///
+    /// Returns `None` when the tile has changed since the chunk was evaluated.
+    pub fn apply_seasonal_change(
+        &mut self,
+        change: TileChanged
+    ) -> Option<TileChanged> {
+        let tile = self.tiles[change.x][change.y];
+        if tile.tile != change.old_tile.tile || self.foliage_type[change.x][change.y] != change.old_foliage {
+            return None;
+        }
 
-        // Clear chunk builder
-        self.clear_builder();
+        self.tiles[change.x][change.y] = change.new_tile;
+        self.foliage_type[change.x][change.y] = change.new_foliage;
 
-        mesh
+        Some(change)
     }
 
-    pub fn clear_builder(
-        &mut self,
-    ) {
-        self.chunk_tile_map_builder.clear();
+    /// Build the chunk mesh, autotiled tiles look at `neighbourhood` to pick their cell and
+    /// other tiles pass `seed` to the material to pick a variant.
+    pub fn generate_mesh (
+        &self,
+        biome_handle: &BiomeHandle,
+        neighbourhood: &TileNeighbourhood,
+        meshing_mode: MeshingMode,
+        tile_atlas: &TileAtlas,
+        seed: &WorldSeed
+    ) -> Mesh {
+        let mut appearances = [[TileAppearance::default(); CHUNK_SIZE]; CHUNK_SIZE];
+
+        for (x, column) in appearances.iter_mut().enumerate() {
+            for (y, appearance) in column.iter_mut().enumerate() {
+                *appearance = self.tile_appearance(biome_handle, neighbourhood, seed, x, y);
+            }
+        }
+
+        let mut chunk_tile_map_builder = ChunkTileMapBuilder::new();
+
+        match meshing_mode {
+            MeshingMode::Tiles => {
+                for (x, column) in appearances.iter().enumerate() {
+                    for (y, appearance) in column.iter().enumerate() {
+                        chunk_tile_map_builder.add_tile(
+                            Vec2::new(x as f32 * TILE_SIZE, y as f32 * TILE_SIZE),
+                            appearance,
+                            tile_atlas);
+                    }
+                }
+            }
+            MeshingMode::Greedy => {
+                for quad in greedy_quads(&appearances) {
+                    chunk_tile_map_builder.add_tiled_quad(
+                        Vec2::new(quad.x as f32 * TILE_SIZE, quad.y as f32 * TILE_SIZE),
+                        Vec2::new(quad.width as f32, quad.height as f32),
+                        &quad.tile,
+                        tile_atlas);
+                }
+            }
+        }
+
+        chunk_tile_map_builder.build()
+    }
+
+    /// Atlas cell, variants, biome tint and animation of a tile, from its definition and biome.
+    fn tile_appearance(
+        &self,
+        biome_handle: &BiomeHandle,
+        neighbourhood: &TileNeighbourhood,
+        seed: &WorldSeed,
+        x: usize,
+        y: usize
+    ) -> TileAppearance {
+        let tile = self.tiles[x][y];
+        let Some(definition) = biome_handle.tiles.get(tile.tile) else {
+            return TileAppearance::default();
+        };
+
+        let (cell, variants) = match (biome_handle.get_autotile(tile.tile), &definition.variants) {
+            (Some(autotile), _) => (autotile.atlas_index(tile.tile, neighbourhood, x, y), TileVariants::default()),
+            (None, Some(variants)) => (definition.atlas_index, TileVariants::from_definition(variants, seed.variant())),
+            (None, None) => (definition.atlas_index, TileVariants::default())
+        };
+
+        let tint = match (definition.tinted, biome_handle.get_biome(tile.biome)) {
+            (true, Ok(biome)) => biome.tint,
+            _ => Color::WHITE
+        };
+
+        TileAppearance {
+            cell,
+            tint,
+            animation: definition.animation,
+            variants,
+            biome: tile.biome
+        }
     }
-}
\ No newline at end of file
+}
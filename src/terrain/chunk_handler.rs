@@ -1,195 +1,497 @@
 use bevy::{
     prelude::*,
 };
-use bevy::sprite::{MaterialMesh2dBundle};
-use bevy::utils::HashSet;
-use crate::terrain::chunk::{Chunk, CHUNK_SIDE_SIZE, CHUNK_SIZE, ChunkCoordinate, TILE_SIZE};
+use bevy::ecs::system::SystemParam;
+use bevy::sprite::{Material2dPlugin, Mesh2dHandle};
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use bevy::utils::{HashMap, HashSet};
+use futures_lite::future;
+use crate::terrain::atlas::TileAtlas;
+use crate::terrain::autotile::TileNeighbourhood;
+use crate::terrain::chunk::{Chunk, CHUNK_SIDE_SIZE, CHUNK_SIZE, ChunkCoordinate, NEIGHBOUR_OFFSETS, TILE_SIZE, tile_to_chunk_coordinate, world_to_chunk_coordinate};
+use crate::terrain::chunk_material::{ChunkMaterial, MAX_WEATHER_BIOMES};
 use crate::entities::player::{Player};
-use crate::terrain::biome::BiomeHandle;
-use crate::terrain::foliage::{FoliageType, get_foliage_paths};
+use crate::terrain::biome::{BiomeHandle, WorldGenError};
+use crate::terrain::foliage::FoliageType;
+use crate::terrain::generator::WorldGenerator;
+use crate::terrain::meshing::MeshingMode;
+use crate::terrain::region::{ChunkSave, RegionStore};
+use crate::terrain::seed::WorldSeed;
+use crate::terrain::tile::{Tile, TileChanged};
 
 pub const VISIBLE_CHUNKS: i32 = 3;
 
+/// Maximum number of generated chunks meshed and spawned in a single frame.
+pub const CHUNKS_SPAWNED_PER_FRAME: usize = 4;
+
 pub struct ChunkHandlerPlugin;
 impl Plugin for ChunkHandlerPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(ChunkHandler {
-            chunks: Vec::new(),
+            chunks: HashMap::new(),
+            chunk_entities: HashMap::new(),
+            chunk_meshes: HashMap::new(),
+            pending_chunks: HashMap::new(),
+            failed_chunks: HashSet::new(),
+            unsaved_chunks: HashMap::new(),
             chunks_to_remesh: Vec::new()
-        }).add_system(update_chunks)
+        })
+            // Replaced by the `SavePlugin` with the region directory of the save slot
+            .init_resource::<RegionStore>()
+            .insert_resource(MeshingMode::from_env())
+            .add_plugin(Material2dPlugin::<ChunkMaterial>::default())
+            .add_startup_system(setup_chunk_materials)
+            .add_system(update_chunk_materials)
+            .add_event::<TileChanged>()
+            .add_system(apply_tile_changes)
+            .add_system(update_chunks.after(apply_tile_changes))
+            .add_system(spawn_generated_chunks)
             .add_system(remove_chunks);
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkError {
+    NotLoaded(IVec2)
+}
+
+impl fmt::Display for ChunkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkError::NotLoaded(coordinate) => write!(f, "Chunk {} is not loaded", coordinate)
+        }
+    }
+}
+
+impl std::error::Error for ChunkError {}
+
 #[derive(Resource)]
 pub struct ChunkHandler {
-    pub chunks: Vec<Chunk>,
-    pub chunks_to_remesh: Vec<Vec2>
+    pub chunks: HashMap<IVec2, Chunk>,
+    pub chunk_entities: HashMap<IVec2, Entity>,
+    /// Mesh of each chunk entity, replaced in place when the chunk is remeshed.
+    pub chunk_meshes: HashMap<IVec2, Handle<Mesh>>,
+    /// Chunks being generated on the async compute task pool.
+    pub pending_chunks: HashMap<IVec2, Task<Result<Chunk, WorldGenError>>>,
+    /// Chunks that failed to generate, they are not retried until the terrain definition
+    /// is reloaded.
+    pub failed_chunks: HashSet<IVec2>,
+    /// Modified chunks unloaded since the last save. They are only written to the region
+    /// files when the world is saved, so the save slot always matches a single save.
+    pub unsaved_chunks: HashMap<IVec2, ChunkSave>,
+    pub chunks_to_remesh: Vec<IVec2>
 }
 
 impl ChunkHandler {
     pub fn contains_chunk(
         &self,
-        chunk_coordinate: Vec2
+        chunk_coordinate: IVec2
     ) -> bool {
-        for chunk in &self.chunks {
-            if chunk.coordinate == chunk_coordinate {
-                return true;
-            }
-        }
+        self.chunks.contains_key(&chunk_coordinate)
+    }
 
-        false
+    /// Whether the chunk is loaded, currently being generated or failed to generate.
+    pub fn is_loaded_or_pending(
+        &self,
+        chunk_coordinate: IVec2
+    ) -> bool {
+        self.contains_chunk(chunk_coordinate)
+            || self.pending_chunks.contains_key(&chunk_coordinate)
+            || self.failed_chunks.contains(&chunk_coordinate)
     }
 
     pub fn get_chunk(
         &mut self,
-        chunk_coordinate: Vec2
-    ) -> &mut Chunk {
-        for chunk in self.chunks.iter_mut() {
-            if chunk.coordinate == chunk_coordinate {
-                return chunk;
-            }
-        }
-
-        panic!("Chunk does not exist")
+        chunk_coordinate: IVec2
+    ) -> Option<&mut Chunk> {
+        self.chunks.get_mut(&chunk_coordinate)
     }
 
-    #[allow(dead_code)]
-    pub fn get_chunk_xy(
-        &mut self,
-        coordinate: Vec2
-    ) -> (&mut Chunk, usize, usize) {
-        let chunk_coord = (coordinate / CHUNK_SIDE_SIZE).floor();
-        let chunk = self.get_chunk(chunk_coord);
+    /// Tile at a world tile coordinate, `None` when its chunk is not loaded.
+    pub fn get_tile(
+        &self,
+        tile_coordinate: IVec2
+    ) -> Option<Tile> {
+        let (chunk_coord, x, y) = tile_to_chunk_coordinate(tile_coordinate);
 
-        // Get x and y array positions.
-        let x = (coordinate.x - chunk.coordinate.x * CHUNK_SIDE_SIZE).floor() as usize;
-        let y = (coordinate.y - chunk.coordinate.y * CHUNK_SIDE_SIZE).floor() as usize;
+        self.chunks.get(&chunk_coord).map(|chunk| chunk.tiles[x][y])
+    }
+
+    /// Foliage at a world tile coordinate, `None` when its chunk is not loaded.
+    pub fn get_foliage(
+        &self,
+        tile_coordinate: IVec2
+    ) -> Option<FoliageType> {
+        let (chunk_coord, x, y) = tile_to_chunk_coordinate(tile_coordinate);
 
-        (chunk, x, y)
+        self.chunks.get(&chunk_coord).map(|chunk| chunk.foliage_type[x][y])
     }
 
-    #[allow(dead_code)]
+    /// Change the tile type at a tile position in a loaded chunk, see `Chunk::set_tile`.
     pub fn update_chunk(
         &mut self,
-        chunk_coordinate: Vec2,
+        chunk_coordinate: IVec2,
         x: usize,
         y: usize,
-        new_block: usize
-    ) {
-        let chunk = self.get_chunk(chunk_coordinate);
-        chunk.set_block(x, y, new_block);
+        new_block: usize,
+        biome_handle: &BiomeHandle
+    ) -> Result<Option<TileChanged>, ChunkError> {
+        let chunk = self.get_chunk(chunk_coordinate)
+            .ok_or(ChunkError::NotLoaded(chunk_coordinate))?;
+
+        Ok(chunk.set_tile(x, y, new_block, biome_handle))
+    }
+
+    /// Change the foliage at a tile position in a loaded chunk, see `Chunk::set_foliage`.
+    pub fn update_foliage(
+        &mut self,
+        chunk_coordinate: IVec2,
+        x: usize,
+        y: usize,
+        foliage_type: FoliageType,
+        biome_handle: &BiomeHandle
+    ) -> Result<Option<TileChanged>, ChunkError> {
+        let chunk = self.get_chunk(chunk_coordinate)
+            .ok_or(ChunkError::NotLoaded(chunk_coordinate))?;
+
+        Ok(chunk.set_foliage(x, y, foliage_type, biome_handle))
+    }
+
+    /// Tiles of the chunk along with the bordering tiles of the loaded chunks around it.
+    pub fn tile_neighbourhood(
+        &self,
+        chunk: &Chunk
+    ) -> TileNeighbourhood {
+        TileNeighbourhood::new(chunk, |tile_coordinate| {
+            self.get_tile(tile_coordinate).map(|tile| tile.tile)
+        })
     }
 
-    #[allow(dead_code)]
     pub fn chunks_to_remesh(
         &mut self,
-        chunk_coord: Vec2
+        chunk_coord: IVec2
     ) {
-        self.chunks_to_remesh.push(chunk_coord);
+        if !self.chunks_to_remesh.contains(&chunk_coord) {
+            self.chunks_to_remesh.push(chunk_coord);
+        }
+    }
+
+    /// Queue a remesh of the loaded chunks around a chunk, whose border tiles depend on it.
+    pub fn remesh_neighbours(
+        &mut self,
+        chunk_coord: IVec2
+    ) {
+        for offset in NEIGHBOUR_OFFSETS {
+            if self.contains_chunk(chunk_coord + offset) {
+                self.chunks_to_remesh(chunk_coord + offset);
+            }
+        }
+    }
+
+    /// Queue a remesh of every loaded chunk containing the tile or one of its neighbours, so
+    /// changes to a border tile also update the chunks next to it.
+    pub fn remesh_around_tile(
+        &mut self,
+        tile_coordinate: IVec2
+    ) {
+        for offset in NEIGHBOUR_OFFSETS.iter().chain([&IVec2::ZERO]) {
+            let (chunk_coord, _, _) = tile_to_chunk_coordinate(tile_coordinate + *offset);
+
+            if self.contains_chunk(chunk_coord) {
+                self.chunks_to_remesh(chunk_coord);
+            }
+        }
+    }
+
+    /// Remove a chunk and its entity from the handler, returning the entity to despawn.
+    ///
+    /// Dropping a pending chunk cancels its generation task. Modified chunks are kept in
+    /// `unsaved_chunks` so they are restored when revisited and written on the next save.
+    pub fn remove_chunk(
+        &mut self,
+        chunk_coordinate: IVec2,
+        biome_handle: &BiomeHandle
+    ) -> Option<Entity> {
+        self.pending_chunks.remove(&chunk_coordinate);
+
+        if let Some(chunk) = self.chunks.remove(&chunk_coordinate) {
+            if chunk.modified {
+                self.unsaved_chunks.insert(chunk_coordinate, chunk.to_save(biome_handle));
+            }
+        }
+
+        self.chunk_meshes.remove(&chunk_coordinate);
+        self.chunk_entities.remove(&chunk_coordinate)
+    }
+
+    /// Drop every loaded, pending and unsaved chunk without saving, returning the entities
+    /// to despawn.
+    pub fn clear(
+        &mut self
+    ) -> Vec<Entity> {
+        self.chunks.clear();
+        self.pending_chunks.clear();
+        self.failed_chunks.clear();
+        self.unsaved_chunks.clear();
+        self.chunks_to_remesh.clear();
+        self.chunk_meshes.clear();
+
+        self.chunk_entities.drain().map(|(_, entity)| entity).collect()
     }
 }
 
 fn remove_chunks(
     mut commands: Commands,
     mut chunk_handler: ResMut<ChunkHandler>,
-    mut player: Query<(&Transform, &mut Player), (With<Player>, Without<ChunkCoordinate>)>,
-    mut chunks: Query<(Entity, &mut ChunkCoordinate), With<ChunkCoordinate>>
+    biome_handle: Res<BiomeHandle>,
+    mut player: Query<(&Transform, &mut Player), With<Player>>
 ) {
     for (transform, mut player) in player.iter_mut() {
         if player.distance_moved > CHUNK_SIDE_SIZE / 2.0 {
             player.distance_moved = 0.;
 
-            let mut chunks_to_remove: HashSet<Entity> = HashSet::new();
-
-            for (chunk_entity, chunk_coordinate) in chunks.iter_mut() {
-                let distance = transform.translation.distance_squared(chunk_coordinate.coordinate.extend(0.0) * CHUNK_SIDE_SIZE);
+            let max_distance = CHUNK_SIDE_SIZE * CHUNK_SIDE_SIZE * VISIBLE_CHUNKS as f32 * VISIBLE_CHUNKS as f32;
+            let chunks_to_remove: Vec<IVec2> = chunk_handler.chunks.keys()
+                .chain(chunk_handler.pending_chunks.keys())
+                .filter(|coordinate| {
+                    let chunk_position = coordinate.as_vec2() * CHUNK_SIDE_SIZE;
+                    transform.translation.truncate().distance_squared(chunk_position) > max_distance
+                })
+                .copied()
+                .collect();
 
-                if distance > CHUNK_SIDE_SIZE * CHUNK_SIDE_SIZE * VISIBLE_CHUNKS as f32 * VISIBLE_CHUNKS as f32 {
-                    chunk_handler.chunks.retain(|chunk| (*chunk).coordinate != chunk_coordinate.coordinate);
-                    chunks_to_remove.insert(chunk_entity);
+            for coordinate in chunks_to_remove {
+                if let Some(chunk_entity) = chunk_handler.remove_chunk(coordinate, &biome_handle) {
+                    commands.entity(chunk_entity).despawn_recursive();
                 }
             }
+        }
+    }
+}
 
-            for chunk_ent in chunks_to_remove.iter() {
-                commands.entity(*chunk_ent).despawn_recursive();
-            }
+/// Handle of the tile atlas material shared by every chunk entity.
+#[derive(Resource)]
+pub struct ChunkMaterialHandle(pub Handle<ChunkMaterial>);
+
+fn setup_chunk_materials(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    tile_atlas: Res<TileAtlas>,
+    mut materials: ResMut<Assets<ChunkMaterial>>
+) {
+    commands.insert_resource(ChunkMaterialHandle(materials.add(ChunkMaterial {
+        color: Color::WHITE,
+        texture: asset_server.load(tile_atlas.texture.as_str()),
+        weather: [Vec4::ZERO; MAX_WEATHER_BIOMES]
+    })));
+}
+
+/// Point the shared chunk material at the atlas texture when the tile atlas changes.
+fn update_chunk_materials(
+    asset_server: Res<AssetServer>,
+    tile_atlas: Res<TileAtlas>,
+    chunk_material: Res<ChunkMaterialHandle>,
+    mut materials: ResMut<Assets<ChunkMaterial>>
+) {
+    if !tile_atlas.is_changed() {
+        return;
+    }
+
+    let texture: Handle<Image> = asset_server.load(tile_atlas.texture.as_str());
+
+    if let Some(material) = materials.get_mut(&chunk_material.0) {
+        if material.texture != texture {
+            material.texture = texture;
         }
     }
 }
 
+/// Assets and settings needed to mesh and spawn chunks.
+#[derive(SystemParam)]
+pub struct ChunkRenderAssets<'w, 's> {
+    pub asset_server: Res<'w, AssetServer>,
+    pub meshing_mode: Res<'w, MeshingMode>,
+    pub tile_atlas: Res<'w, TileAtlas>,
+    pub chunk_material: Res<'w, ChunkMaterialHandle>,
+    pub meshes: ResMut<'w, Assets<Mesh>>,
+    pub world_seed: Res<'w, WorldSeed>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>
+}
+
 pub fn update_chunks(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut render_assets: ChunkRenderAssets,
     players: Query<&Player, With<Player>>,
-    chunks: Query<(Entity, &mut ChunkCoordinate), (With<ChunkCoordinate>, Without<Player>)>,
     mut chunk_handler: ResMut<ChunkHandler>,
-    biome_handle: ResMut<BiomeHandle>
+    biome_handle: Res<BiomeHandle>,
+    region_store: Res<RegionStore>
 ) {
+    let chunk_handler = chunk_handler.as_mut();
+
     // Remesh Chunks
-    for coord_to_remesh in chunk_handler.chunks_to_remesh.clone() {
-        for (entity, chunk_coord) in chunks.iter() {
-            if chunk_coord.coordinate == coord_to_remesh {
-                // Get chunk to remesh
-                let mut chunk = chunk_handler.get_chunk(coord_to_remesh);
-
-                // De-spawn old chunk
-                commands.entity(entity).despawn_recursive();
-
-                // Spawn new chunk
-                spawn_chunk(&mut commands,
-                            &asset_server,
-                            &mut meshes,
-                            &mut materials,
-                            &mut chunk);
+    for coord_to_remesh in std::mem::take(&mut chunk_handler.chunks_to_remesh) {
+        let Some(chunk) = chunk_handler.chunks.get(&coord_to_remesh) else {
+            continue;
+        };
+
+        let neighbourhood = chunk_handler.tile_neighbourhood(chunk);
+
+        match chunk_handler.chunk_meshes.get(&coord_to_remesh) {
+            // Replace the mesh asset in place, foliage children are kept up to date by
+            // `apply_tile_changes`
+            Some(mesh_handle) => {
+                let mesh = chunk.generate_mesh(&biome_handle,
+                                               &neighbourhood,
+                                               *render_assets.meshing_mode,
+                                               &render_assets.tile_atlas,
+                                               &render_assets.world_seed);
+
+                if let Some(chunk_mesh) = render_assets.meshes.get_mut(mesh_handle) {
+                    *chunk_mesh = mesh;
+                }
+            }
+            None => {
+                let (entity, mesh_handle) = spawn_chunk(&mut commands,
+                                                        &mut render_assets,
+                                                        &biome_handle,
+                                                        &neighbourhood,
+                                                        chunk);
+
+                chunk_handler.chunk_entities.insert(coord_to_remesh, entity);
+                chunk_handler.chunk_meshes.insert(coord_to_remesh, mesh_handle);
             }
         }
     }
 
-    chunk_handler.chunks_to_remesh.clear();
+    // Queue chunk generation, once the terrain definition has loaded
+    if !biome_handle.is_loaded() {
+        return;
+    }
+
+    let pool = AsyncComputeTaskPool::get();
+    let mut generator: Option<Arc<WorldGenerator>> = None;
 
-    // Generate Chunks
     for player in players.iter() {
-        let player_coordinate = Vec2::new((player.pos.x / CHUNK_SIDE_SIZE).floor(), (player.pos.y / CHUNK_SIDE_SIZE).floor());
+        let player_coordinate = world_to_chunk_coordinate(player.pos);
 
         for x in (-VISIBLE_CHUNKS+1)..VISIBLE_CHUNKS {
             for y in (-VISIBLE_CHUNKS+1)..VISIBLE_CHUNKS {
-                let coord = player_coordinate + Vec2::new(x as f32, y as f32);
-                if !chunk_handler.contains_chunk(coord) {
-                    let mut chunk = Chunk::new(coord, 0, &biome_handle);
+                let coord = player_coordinate + IVec2::new(x, y);
+                if !chunk_handler.is_loaded_or_pending(coord) {
+                    let generator = generator
+                        .get_or_insert_with(|| Arc::new(WorldGenerator::new(*render_assets.world_seed, biome_handle.clone())))
+                        .clone();
+                    let region_store = region_store.clone();
+                    let unsaved_chunk = chunk_handler.unsaved_chunks.get(&coord).cloned();
 
-                    spawn_chunk(&mut commands,
-                                &asset_server,
-                                &mut meshes,
-                                &mut materials,
-                                &mut chunk);
+                    let task = pool.spawn(async move {
+                        load_or_generate_chunk(coord, &generator, &region_store, unsaved_chunk)
+                    });
 
-                    chunk_handler.chunks.push(chunk);
+                    chunk_handler.pending_chunks.insert(coord, task);
                 }
             }
         }
     }
 }
 
+/// Restore the unsaved or saved version of a chunk if it has been modified before,
+/// otherwise generate it.
+fn load_or_generate_chunk(
+    coordinate: IVec2,
+    generator: &WorldGenerator,
+    region_store: &RegionStore,
+    unsaved_chunk: Option<ChunkSave>
+) -> Result<Chunk, WorldGenError> {
+    if let Some(chunk_save) = unsaved_chunk {
+        return Ok(Chunk::from_save(coordinate, chunk_save, generator.biome_handle()));
+    }
+
+    match region_store.load_chunk(coordinate) {
+        Ok(Some(chunk_save)) => Ok(Chunk::from_save(coordinate, chunk_save, generator.biome_handle())),
+        Ok(None) => generator.generate_chunk(coordinate).map(Chunk::from_data),
+        Err(error) => {
+            error!("Failed to load chunk {}, regenerating: {}", coordinate, error);
+            generator.generate_chunk(coordinate).map(Chunk::from_data)
+        }
+    }
+}
+
+/// Mesh and spawn chunks whose generation task has finished, at most
+/// `CHUNKS_SPAWNED_PER_FRAME` per frame.
+fn spawn_generated_chunks(
+    mut commands: Commands,
+    mut render_assets: ChunkRenderAssets,
+    mut chunk_handler: ResMut<ChunkHandler>,
+    biome_handle: Res<BiomeHandle>
+) {
+    let chunk_handler = chunk_handler.as_mut();
+    let mut generated_chunks: Vec<(IVec2, Result<Chunk, WorldGenError>)> = Vec::new();
+
+    for (coord, task) in chunk_handler.pending_chunks.iter_mut() {
+        if generated_chunks.len() >= CHUNKS_SPAWNED_PER_FRAME {
+            break;
+        }
+
+        if let Some(chunk) = future::block_on(future::poll_once(task)) {
+            generated_chunks.push((*coord, chunk));
+        }
+    }
+
+    for (coord, chunk) in generated_chunks {
+        chunk_handler.pending_chunks.remove(&coord);
+
+        let mut chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(error) => {
+                error!("Failed to generate chunk {}: {}", coord, error);
+                chunk_handler.failed_chunks.insert(coord);
+                continue;
+            }
+        };
+
+        // Restored from its unsaved version, which is kept on the chunk until the next save
+        if chunk_handler.unsaved_chunks.remove(&coord).is_some() {
+            chunk.modified = true;
+        }
+
+        let neighbourhood = chunk_handler.tile_neighbourhood(&chunk);
+        let (entity, mesh_handle) = spawn_chunk(&mut commands,
+                                                &mut render_assets,
+                                                &biome_handle,
+                                                &neighbourhood,
+                                                &chunk);
+
+        chunk_handler.chunks.insert(coord, chunk);
+        chunk_handler.chunk_entities.insert(coord, entity);
+        chunk_handler.chunk_meshes.insert(coord, mesh_handle);
+
+        // Border tiles of the neighbours were meshed without this chunk
+        chunk_handler.remesh_neighbours(coord);
+    }
+}
+
+/// Spawn the chunk entity and its foliage, returning the entity and the handle of its mesh.
 pub fn spawn_chunk(
     commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<ColorMaterial>>,
-    chunk: &mut Chunk
-) {
-    let mesh = chunk.generate_mesh();
+    render_assets: &mut ChunkRenderAssets,
+    biome_handle: &BiomeHandle,
+    neighbourhood: &TileNeighbourhood,
+    chunk: &Chunk
+) -> (Entity, Handle<Mesh>) {
+    let ChunkRenderAssets { asset_server, meshing_mode, tile_atlas, chunk_material, meshes, world_seed, .. } = render_assets;
+    let mesh_handle = meshes.add(chunk.generate_mesh(biome_handle, neighbourhood, **meshing_mode, tile_atlas, world_seed));
 
     let chunk_entity = commands.spawn((ChunkCoordinate {
         coordinate: chunk.coordinate
-    }, MaterialMesh2dBundle  {
-        mesh: meshes.add(mesh).into(),
-        material: materials.add(ColorMaterial::from(asset_server.load("tiles/tiles.png"))),
+    }, Mesh2dHandle(mesh_handle.clone()), chunk_material.0.clone(), SpatialBundle {
         transform: Transform::from_xyz(
-            chunk.coordinate.x * CHUNK_SIDE_SIZE,
-            chunk.coordinate.y * CHUNK_SIDE_SIZE,
+            chunk.coordinate.x as f32 * CHUNK_SIDE_SIZE,
+            chunk.coordinate.y as f32 * CHUNK_SIDE_SIZE,
             0.0),
         ..Default::default()
     })).id();
@@ -197,17 +499,84 @@ pub fn spawn_chunk(
     for x in 0..CHUNK_SIZE {
         for y in 0..CHUNK_SIZE {
             if chunk.foliage_type[x][y] != FoliageType::NONE {
-                let foliage_entity = commands.spawn(SpriteBundle {
-                    texture: asset_server.load(get_foliage_paths(chunk.foliage_type[x][y])),
-                    transform: Transform::from_xyz(
-                        x as f32 * TILE_SIZE + TILE_SIZE / 2.0,
-                        y as f32 * TILE_SIZE + TILE_SIZE / 2.0,
-                        1.0).with_scale(Vec3::splat(1.0 / 16.0)),
-                    ..default()
-                }).id();
-
-                commands.entity(foliage_entity).set_parent(chunk_entity);
+                spawn_foliage(commands, asset_server, biome_handle, chunk_entity, x, y, chunk.foliage_type[x][y]);
             }
         }
     }
-}
\ No newline at end of file
+
+    (chunk_entity, mesh_handle)
+}
+
+/// Foliage sprite, a child of the chunk entity at tile position `x`, `y`.
+#[derive(Component)]
+pub struct FoliageSprite {
+    pub x: usize,
+    pub y: usize
+}
+
+pub fn spawn_foliage(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    biome_handle: &BiomeHandle,
+    chunk_entity: Entity,
+    x: usize,
+    y: usize,
+    foliage_type: FoliageType
+) -> Option<Entity> {
+    let foliage = biome_handle.get_foliage(foliage_type)?;
+
+    let foliage_entity = commands.spawn((SpriteBundle {
+        texture: asset_server.load(foliage.sprite.as_str()),
+        transform: Transform::from_xyz(
+            x as f32 * TILE_SIZE + TILE_SIZE / 2.0,
+            y as f32 * TILE_SIZE + TILE_SIZE / 2.0,
+            1.0).with_scale(Vec3::splat(foliage.scale)),
+        ..default()
+    }, FoliageSprite {
+        x,
+        y
+    })).id();
+
+    commands.entity(foliage_entity).set_parent(chunk_entity);
+
+    Some(foliage_entity)
+}
+
+/// Remesh chunks whose tiles or neighbouring tiles changed and replace the foliage sprite of changed foliage.
+fn apply_tile_changes(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut tile_changed_events: EventReader<TileChanged>,
+    mut chunk_handler: ResMut<ChunkHandler>,
+    biome_handle: Res<BiomeHandle>,
+    chunk_children: Query<&Children>,
+    foliage_sprites: Query<&FoliageSprite>
+) {
+    for change in tile_changed_events.iter() {
+        if change.old_tile.tile != change.new_tile.tile {
+            chunk_handler.remesh_around_tile(change.tile_coordinate());
+        }
+
+        if !change.foliage_changed() {
+            continue;
+        }
+
+        let Some(chunk_entity) = chunk_handler.chunk_entities.get(&change.chunk_coordinate).copied() else {
+            continue;
+        };
+
+        if let Ok(children) = chunk_children.get(chunk_entity) {
+            for child in children.iter() {
+                if let Ok(sprite) = foliage_sprites.get(*child) {
+                    if sprite.x == change.x && sprite.y == change.y {
+                        commands.entity(*child).despawn_recursive();
+                    }
+                }
+            }
+        }
+
+        if change.new_foliage != FoliageType::NONE {
+            spawn_foliage(&mut commands, &asset_server, &biome_handle, chunk_entity, change.x, change.y, change.new_foliage);
+        }
+    }
+}
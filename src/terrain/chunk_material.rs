@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::render::mesh::{MeshVertexAttribute, MeshVertexBufferLayout};
+use bevy::render::render_resource::{AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError, VertexFormat};
+use bevy::sprite::{Material2d, Material2dKey};
+
+pub const CHUNK_SHADER_PATH: &str = "shaders/chunk.wgsl";
+
+/// Biomes with their own ground weather in `ChunkMaterial::weather`, the length of the
+/// `weather` array in the shader. Biomes past it stay dry.
+pub const MAX_WEATHER_BIOMES: usize = 32;
+
+/// Offset and size in uv coordinates of the atlas cell repeated across a quad.
+pub const ATTRIBUTE_ATLAS_RECT: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_AtlasRect", 582_149_307, VertexFormat::Float32x4);
+
+/// Colour multiplied with the tile, i.e. the biome tint of grass.
+pub const ATTRIBUTE_TINT: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Tint", 582_149_308, VertexFormat::Float32x4);
+
+/// Frame count, frames per second and the uv offset between frames of animated tiles.
+pub const ATTRIBUTE_ANIMATION: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Animation", 582_149_309, VertexFormat::Float32x4);
+
+/// Variants a tile picks from by hashing its world position with the seed in `y`. `x` sets
+/// random quarter turns in the lowest bit, a random horizontal flip in the second bit and
+/// the number of `ATTRIBUTE_VARIANT_CELLS` above them.
+pub const ATTRIBUTE_VARIANT: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Variant", 582_149_310, VertexFormat::Uint32x2);
+
+/// Uv offsets from the atlas cell to up to two alternative cells of a tile.
+pub const ATTRIBUTE_VARIANT_CELLS: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_VariantCells", 582_149_311, VertexFormat::Float32x4);
+
+/// `BiomeType` of the tile, indexing `ChunkMaterial::weather`.
+pub const ATTRIBUTE_BIOME: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Biome", 582_149_312, VertexFormat::Uint32);
+
+/// Material of chunk meshes, repeating an atlas cell across quads that may span several
+/// tiles.
+///
+/// Meshes need uvs counting tiles across the quad along with the `ATTRIBUTE_ATLAS_RECT`,
+/// `ATTRIBUTE_TINT`, `ATTRIBUTE_ANIMATION`, `ATTRIBUTE_VARIANT`, `ATTRIBUTE_VARIANT_CELLS`
+/// and `ATTRIBUTE_BIOME` attributes, as built by `ChunkTileMapBuilder`. `color` tints every
+/// chunk, i.e. for lighting, and `weather` holds the wetness and snow cover of the ground
+/// of each biome in `x` and `y`.
+#[derive(AsBindGroup, TypeUuid, Clone)]
+#[uuid = "3c7d9a0e-5b24-4f61-8e3a-d2b7f14c6a95"]
+pub struct ChunkMaterial {
+    #[uniform(0)]
+    pub color: Color,
+    #[texture(1)]
+    #[sampler(2)]
+    pub texture: Handle<Image>,
+    #[uniform(3)]
+    pub weather: [Vec4; MAX_WEATHER_BIOMES]
+}
+
+impl Material2d for ChunkMaterial {
+    fn vertex_shader() -> ShaderRef {
+        CHUNK_SHADER_PATH.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        CHUNK_SHADER_PATH.into()
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayout,
+        _key: Material2dKey<Self>
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let vertex_layout = layout.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_UV_0.at_shader_location(1),
+            ATTRIBUTE_ATLAS_RECT.at_shader_location(2),
+            ATTRIBUTE_TINT.at_shader_location(3),
+            ATTRIBUTE_ANIMATION.at_shader_location(4),
+            ATTRIBUTE_VARIANT.at_shader_location(5),
+            ATTRIBUTE_VARIANT_CELLS.at_shader_location(6),
+            ATTRIBUTE_BIOME.at_shader_location(7)
+        ])?;
+
+        descriptor.vertex.buffers = vec![vertex_layout];
+
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use crate::terrain::biome::BiomeType;
+use crate::terrain::noise::NoiseSettings;
+
+/// Default noise of the temperature field, kept low frequency so climate zones span many
+/// chunks.
+pub const TEMPERATURE_NOISE: NoiseSettings = NoiseSettings::new(0.05, 3);
+pub const MOISTURE_NOISE: NoiseSettings = NoiseSettings::new(0.08, 3);
+pub const ELEVATION_NOISE: NoiseSettings = NoiseSettings::new(0.1, 4);
+
+/// Climate lookup resolved from a `ClimateDefinition`, see its documentation for the layout.
+#[derive(Clone)]
+pub struct ClimateTable {
+    pub sea_level: f32,
+    pub beach_level: f32,
+    pub ocean: BiomeType,
+    pub beach: BiomeType,
+    pub temperature_bands: Vec<f32>,
+    pub moisture_bands: Vec<f32>,
+    pub table: Vec<Vec<BiomeType>>
+}
+
+impl ClimateTable {
+    pub fn get_biome(
+        &self,
+        temperature: f32,
+        moisture: f32,
+        elevation: f32
+    ) -> BiomeType {
+        if elevation < self.sea_level {
+            return self.ocean;
+        }
+
+        if elevation < self.beach_level {
+            return self.beach;
+        }
+
+        let row = band(&self.temperature_bands, temperature);
+        let column = band(&self.moisture_bands, moisture);
+
+        self.table[row][column]
+    }
+}
+
+/// Index of the band the value falls in, given the upper bound of each band but the last.
+fn band(
+    upper_bounds: &[f32],
+    value: f32
+) -> usize {
+    upper_bounds.iter()
+        .position(|upper_bound| value < *upper_bound)
+        .unwrap_or(upper_bounds.len())
+}
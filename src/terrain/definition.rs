@@ -0,0 +1,532 @@
+use std::collections::HashSet;
+use std::fmt;
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+use crate::terrain::biome::WorldGenError;
+use crate::terrain::biome_map::BIOME_NOISE;
+use crate::terrain::chunk::CHUNK_SIZE;
+use crate::terrain::climate::{ELEVATION_NOISE, MOISTURE_NOISE, TEMPERATURE_NOISE};
+use crate::terrain::generator::{FOLIAGE_NOISE, TERRAIN_NOISE};
+use crate::terrain::noise::NoiseSettings;
+use crate::terrain::season::Season;
+use crate::weather::Weather;
+
+/// Asset path of the tile, foliage and biome definitions loaded at startup.
+pub const TERRAIN_DEFINITION_PATH: &str = "terrain/default.terrain.ron";
+
+/// Tiles, foliage and biomes of a world, loaded from a `.terrain.ron` asset file.
+///
+/// Definitions reference each other by their string id, ids are resolved to indices when
+/// the definition is turned into a `BiomeHandle`.
+#[derive(Deserialize, TypeUuid, Clone)]
+#[uuid = "8f6c3a52-7d1e-4b9a-a3c5-2e6f0d4b9c17"]
+pub struct TerrainDefinition {
+    pub tiles: Vec<TileDefinition>,
+    pub foliage: Vec<FoliageDefinition>,
+    pub biomes: Vec<BiomeDefinition>,
+    /// Climate lookup table, biomes are picked by weight when it is missing.
+    #[serde(default)]
+    pub climate: Option<ClimateDefinition>,
+    /// Blending of tiles near biome borders, borders are hard when it is missing.
+    #[serde(default)]
+    pub blend: Option<BiomeBlendDefinition>,
+    /// Noise layers the terrain is generated from, layers left out keep their defaults.
+    #[serde(default)]
+    pub noise: NoiseDefinition
+}
+
+#[derive(Deserialize, Clone)]
+pub struct TileDefinition {
+    pub id: String,
+    /// Cell of the tile in the `TileAtlas`, numbered from the top left by rows.
+    pub atlas_index: usize,
+    /// Transition cells picked from the neighbouring tiles, `atlas_index` is used when
+    /// it is missing.
+    #[serde(default)]
+    pub autotile: Option<AutotileDefinition>,
+    /// Whether the tile is multiplied by the `tint` of the biome it is in.
+    #[serde(default)]
+    pub tinted: bool,
+    /// Frames cycled through over time, the tile is static when it is missing.
+    #[serde(default)]
+    pub animation: Option<TileAnimation>,
+    /// Alternative looks picked from the tile position, ignored by autotiled tiles.
+    #[serde(default)]
+    pub variants: Option<TileVariantsDefinition>
+}
+
+/// Most `cells` a tile can list in its `variants`.
+pub const MAX_VARIANT_CELLS: usize = 2;
+
+/// Visual variety of a tile, each tile picks one of `atlas_index` and `cells`, optionally
+/// turned by a random number of quarter turns and flipped, from a hash of its position.
+/// The pick is made by `ChunkMaterial` so neighbouring tiles still merge in greedy meshing.
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct TileVariantsDefinition {
+    /// Atlas cells used alongside `atlas_index`, at most `MAX_VARIANT_CELLS`.
+    #[serde(default)]
+    pub cells: Vec<usize>,
+    #[serde(default)]
+    pub rotate: bool,
+    #[serde(default)]
+    pub flip: bool
+}
+
+/// Animation of a tile cycling through `frames` atlas cells, each frame `frame_offset`
+/// cells after the previous one so autotiled cells animate along with their set.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct TileAnimation {
+    pub frames: u32,
+    pub frame_offset: usize,
+    pub frames_per_second: f32
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AutotileMode {
+    /// 16 cells, one for each combination of connected north, east, south and west
+    /// neighbours with north as the lowest bit.
+    Edges,
+    /// 47 cells, the "blob" set, also covering inner corners where both edges connect but
+    /// the diagonal neighbour does not. Cells follow the ascending order of the 8 bit
+    /// neighbour masks, going clockwise from north as the lowest bit.
+    Blob
+}
+
+impl AutotileMode {
+    /// Number of consecutive atlas cells in the tile set.
+    pub fn cell_count(self) -> usize {
+        match self {
+            AutotileMode::Edges => 16,
+            AutotileMode::Blob => 47
+        }
+    }
+}
+
+/// Tiles pick a cell starting at `atlas_start` from which neighbours connect to them.
+/// Neighbours of the same tile type always connect.
+#[derive(Deserialize, Clone, Debug)]
+pub struct AutotileDefinition {
+    pub mode: AutotileMode,
+    pub atlas_start: usize,
+    /// Ids of other tiles that connect.
+    #[serde(default)]
+    pub connects_to: Vec<String>
+}
+
+#[derive(Deserialize, Clone)]
+pub struct FoliageDefinition {
+    pub id: String,
+    pub sprite: String,
+    pub weight: u16,
+    pub scale: f32,
+    /// Ids of the tiles the foliage can grow on.
+    pub spawns_on: Vec<String>
+}
+
+#[derive(Deserialize, Clone)]
+pub struct BiomeDefinition {
+    pub id: String,
+    pub weight: u16,
+    pub foliage_density: f32,
+    pub tiles: Vec<BiomeTileDefinition>,
+    /// Ids of the foliage that grows in the biome.
+    #[serde(default)]
+    pub foliage: Vec<String>,
+    /// Rgb colour multiplied with the tinted tiles of the biome, white when it is missing.
+    #[serde(default)]
+    pub tint: Option<[f32; 3]>,
+    /// Changes to the biome in some seasons, seasons without an entry use the fields above.
+    #[serde(default)]
+    pub seasons: Vec<BiomeSeasonDefinition>,
+    /// Markov chain of the weather over the biome, the sky stays clear when it is empty.
+    #[serde(default)]
+    pub weather: Vec<WeatherTransitionDefinition>,
+    /// Rgb colour of the biome on exported maps, picked from the biome's index when missing.
+    #[serde(default)]
+    pub map_color: Option<[f32; 3]>
+}
+
+/// Weights of the weather following `from` at each weather step, weather without an entry
+/// clears up.
+#[derive(Deserialize, Clone)]
+pub struct WeatherTransitionDefinition {
+    pub from: Weather,
+    pub to: Vec<WeatherWeightDefinition>
+}
+
+#[derive(Deserialize, Clone)]
+pub struct WeatherWeightDefinition {
+    pub weather: Weather,
+    pub weight: u16
+}
+
+/// Tiles, foliage density and tint of a biome replaced during one season, missing fields
+/// keep the biome's own value.
+///
+/// Tiles are picked from the same noise in every season, so `tiles` must keep the order
+/// and weights of the biome's tile table and only swap tiles, i.e. grass for snow. That
+/// way a season changes just those tiles and the next season can tell which tiles grew
+/// naturally and which were placed by the player.
+#[derive(Deserialize, Clone)]
+pub struct BiomeSeasonDefinition {
+    pub season: Season,
+    #[serde(default)]
+    pub tiles: Option<Vec<BiomeTileDefinition>>,
+    #[serde(default)]
+    pub foliage_density: Option<f32>,
+    #[serde(default)]
+    pub tint: Option<[f32; 3]>
+}
+
+#[derive(Deserialize, Clone)]
+pub struct BiomeTileDefinition {
+    pub tile: String,
+    pub weight: u16
+}
+
+/// Whittaker style biome lookup from temperature, moisture and elevation noise.
+///
+/// Tiles below `sea_level` elevation are ocean and tiles below `beach_level` are beach,
+/// land biomes are looked up in `table`. Rows of the table go from cold to hot and columns
+/// from dry to wet, split by the upper bounds in `temperature_bands` and `moisture_bands`.
+#[derive(Deserialize, Clone)]
+pub struct ClimateDefinition {
+    pub sea_level: f32,
+    pub beach_level: f32,
+    pub ocean: String,
+    pub beach: String,
+    pub temperature_bands: Vec<f32>,
+    pub moisture_bands: Vec<f32>,
+    pub table: Vec<Vec<String>>
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BiomeBlendMode {
+    /// Ordered 4x4 dithering.
+    Dither,
+    /// White noise hashed from the tile position.
+    Noise
+}
+
+/// Tiles within `width` tiles of a biome border pick from a weighted mix of the nearby
+/// biomes' tile tables.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct BiomeBlendDefinition {
+    /// At most `CHUNK_SIZE`, the biomes of a border this wide around each chunk are sampled
+    /// when it is generated.
+    pub width: usize,
+    pub mode: BiomeBlendMode
+}
+
+/// Settings of every noise layer of the world generation.
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct NoiseDefinition {
+    /// Picks the tile of each position from its biome's tile table.
+    pub terrain: NoiseSettings,
+    /// Places foliage, compared against the biome's foliage density.
+    pub foliage: NoiseSettings,
+    /// Picks biomes by weight when there is no climate table.
+    pub biome: NoiseSettings,
+    pub temperature: NoiseSettings,
+    pub moisture: NoiseSettings,
+    pub elevation: NoiseSettings
+}
+
+impl Default for NoiseDefinition {
+    fn default() -> Self {
+        NoiseDefinition {
+            terrain: TERRAIN_NOISE,
+            foliage: FOLIAGE_NOISE,
+            biome: BIOME_NOISE,
+            temperature: TEMPERATURE_NOISE,
+            moisture: MOISTURE_NOISE,
+            elevation: ELEVATION_NOISE
+        }
+    }
+}
+
+impl NoiseDefinition {
+    fn validate(
+        &self
+    ) -> Result<(), TerrainDefinitionError> {
+        let layers = [
+            ("terrain", &self.terrain),
+            ("foliage", &self.foliage),
+            ("biome", &self.biome),
+            ("temperature", &self.temperature),
+            ("moisture", &self.moisture),
+            ("elevation", &self.elevation)
+        ];
+
+        for (layer, settings) in layers {
+            settings.validate()
+                .map_err(|reason| TerrainDefinitionError::InvalidNoise { layer, reason })?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TerrainDefinitionError {
+    DuplicateId { kind: &'static str, id: String },
+    UnknownTile { referenced_by: String, tile: String },
+    UnknownFoliage { biome: String, foliage: String },
+    UnknownBiome { biome: String },
+    InvalidAnimation { tile: String },
+    TooManyVariantCells { tile: String, count: usize },
+    InvalidAtlasCell { tile: String, cell: usize, cell_count: usize },
+    InvalidSeasonTiles { biome: String, season: Season },
+    InvalidClimateTable { reason: String },
+    InvalidBlendWidth { width: usize },
+    InvalidNoise { layer: &'static str, reason: String },
+    /// A biome failed the checks made when it was registered in the `BiomeHandle`.
+    InvalidBiome(WorldGenError)
+}
+
+impl fmt::Display for TerrainDefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TerrainDefinitionError::DuplicateId { kind, id } =>
+                write!(f, "Duplicate {} id '{}'", kind, id),
+            TerrainDefinitionError::UnknownTile { referenced_by, tile } =>
+                write!(f, "'{}' references unknown tile '{}'", referenced_by, tile),
+            TerrainDefinitionError::UnknownFoliage { biome, foliage } =>
+                write!(f, "Biome '{}' references unknown foliage '{}'", biome, foliage),
+            TerrainDefinitionError::UnknownBiome { biome } =>
+                write!(f, "Climate table references unknown biome '{}'", biome),
+            TerrainDefinitionError::InvalidAnimation { tile } =>
+                write!(f, "Tile '{}' has an animation without frames", tile),
+            TerrainDefinitionError::TooManyVariantCells { tile, count } =>
+                write!(f, "Tile '{}' has {} variant cells, at most {} are allowed", tile, count, MAX_VARIANT_CELLS),
+            TerrainDefinitionError::InvalidAtlasCell { tile, cell, cell_count } =>
+                write!(f, "Tile '{}' uses atlas cell {} but the tile atlas only has {} cells", tile, cell, cell_count),
+            TerrainDefinitionError::InvalidSeasonTiles { biome, season } =>
+                write!(f, "{:?} tiles of biome '{}' must have the same weights in the same order as the biome's tiles", season, biome),
+            TerrainDefinitionError::InvalidClimateTable { reason } =>
+                write!(f, "Invalid climate table: {}", reason),
+            TerrainDefinitionError::InvalidBlendWidth { width } =>
+                write!(f, "Blend width {} is wider than a chunk, at most {} is allowed", width, CHUNK_SIZE),
+            TerrainDefinitionError::InvalidNoise { layer, reason } =>
+                write!(f, "Invalid {} noise: {}", layer, reason),
+            TerrainDefinitionError::InvalidBiome(error) =>
+                write!(f, "{}", error)
+        }
+    }
+}
+
+impl std::error::Error for TerrainDefinitionError {}
+
+impl From<WorldGenError> for TerrainDefinitionError {
+    fn from(error: WorldGenError) -> Self {
+        TerrainDefinitionError::InvalidBiome(error)
+    }
+}
+
+impl TerrainDefinition {
+    /// Parse and validate a definition from the contents of a `.terrain.ron` file.
+    pub fn from_bytes(
+        bytes: &[u8]
+    ) -> Result<Self, anyhow::Error> {
+        let definition: TerrainDefinition = ron::de::from_bytes(bytes)?;
+        definition.validate()?;
+
+        Ok(definition)
+    }
+
+    /// Check ids are unique and every tile and foliage reference resolves.
+    pub fn validate(
+        &self
+    ) -> Result<(), TerrainDefinitionError> {
+        let tile_ids = unique_ids("tile", self.tiles.iter().map(|tile| &tile.id))?;
+        let foliage_ids = unique_ids("foliage", self.foliage.iter().map(|foliage| &foliage.id))?;
+        let biome_ids = unique_ids("biome", self.biomes.iter().map(|biome| &biome.id))?;
+
+        for tile in &self.tiles {
+            if tile.animation.is_some_and(|animation| animation.frames == 0) {
+                return Err(TerrainDefinitionError::InvalidAnimation {
+                    tile: tile.id.clone()
+                });
+            }
+
+            if let Some(variants) = tile.variants.as_ref().filter(|variants| variants.cells.len() > MAX_VARIANT_CELLS) {
+                return Err(TerrainDefinitionError::TooManyVariantCells {
+                    tile: tile.id.clone(),
+                    count: variants.cells.len()
+                });
+            }
+
+            let connects_to = tile.autotile.iter().flat_map(|autotile| &autotile.connects_to);
+
+            for connected_tile in connects_to {
+                if !tile_ids.contains(connected_tile.as_str()) {
+                    return Err(TerrainDefinitionError::UnknownTile {
+                        referenced_by: tile.id.clone(),
+                        tile: connected_tile.clone()
+                    });
+                }
+            }
+        }
+
+        for foliage in &self.foliage {
+            for tile in &foliage.spawns_on {
+                if !tile_ids.contains(tile.as_str()) {
+                    return Err(TerrainDefinitionError::UnknownTile {
+                        referenced_by: foliage.id.clone(),
+                        tile: tile.clone()
+                    });
+                }
+            }
+        }
+
+        for biome in &self.biomes {
+            let season_tiles = biome.seasons.iter()
+                .filter_map(|season| season.tiles.as_ref())
+                .flatten();
+
+            for tile in biome.tiles.iter().chain(season_tiles) {
+                if !tile_ids.contains(tile.tile.as_str()) {
+                    return Err(TerrainDefinitionError::UnknownTile {
+                        referenced_by: biome.id.clone(),
+                        tile: tile.tile.clone()
+                    });
+                }
+            }
+
+            for season in &biome.seasons {
+                let Some(season_tiles) = &season.tiles else {
+                    continue;
+                };
+
+                let same_weights = season_tiles.len() == biome.tiles.len() && season_tiles.iter()
+                    .zip(&biome.tiles)
+                    .all(|(season_tile, tile)| season_tile.weight == tile.weight);
+
+                if !same_weights {
+                    return Err(TerrainDefinitionError::InvalidSeasonTiles {
+                        biome: biome.id.clone(),
+                        season: season.season
+                    });
+                }
+            }
+
+            for foliage in &biome.foliage {
+                if !foliage_ids.contains(foliage.as_str()) {
+                    return Err(TerrainDefinitionError::UnknownFoliage {
+                        biome: biome.id.clone(),
+                        foliage: foliage.clone()
+                    });
+                }
+            }
+        }
+
+        if let Some(climate) = &self.climate {
+            climate.validate(&biome_ids)?;
+        }
+
+        if let Some(blend) = self.blend.filter(|blend| blend.width > CHUNK_SIZE) {
+            return Err(TerrainDefinitionError::InvalidBlendWidth {
+                width: blend.width
+            });
+        }
+
+        self.noise.validate()?;
+
+        Ok(())
+    }
+}
+
+impl ClimateDefinition {
+    fn validate(
+        &self,
+        biome_ids: &HashSet<&str>
+    ) -> Result<(), TerrainDefinitionError> {
+        let invalid = |reason: String| Err(TerrainDefinitionError::InvalidClimateTable { reason });
+
+        if self.beach_level < self.sea_level {
+            return invalid("beach_level is below sea_level".to_string());
+        }
+
+        if self.table.len() != self.temperature_bands.len() + 1 {
+            return invalid(format!(
+                "{} temperature bands need {} rows, found {}",
+                self.temperature_bands.len(), self.temperature_bands.len() + 1, self.table.len()));
+        }
+
+        for row in &self.table {
+            if row.len() != self.moisture_bands.len() + 1 {
+                return invalid(format!(
+                    "{} moisture bands need {} columns, found {}",
+                    self.moisture_bands.len(), self.moisture_bands.len() + 1, row.len()));
+            }
+        }
+
+        // Out of order bands leave some rows or columns of the table unreachable
+        for (name, bands) in [("temperature_bands", &self.temperature_bands), ("moisture_bands", &self.moisture_bands)] {
+            if let Some(band) = bands.iter().find(|band| !(0.0..=1.0).contains(*band)) {
+                return invalid(format!("{} must be between 0 and 1, found {}", name, band));
+            }
+
+            if let Some(pair) = bands.windows(2).find(|pair| pair[0] >= pair[1]) {
+                return invalid(format!("{} must be strictly ascending, found {} before {}", name, pair[0], pair[1]));
+            }
+        }
+
+        let biomes = self.table.iter()
+            .flatten()
+            .chain([&self.ocean, &self.beach]);
+
+        for biome in biomes {
+            if !biome_ids.contains(biome.as_str()) {
+                return Err(TerrainDefinitionError::UnknownBiome {
+                    biome: biome.clone()
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn unique_ids<'a>(
+    kind: &'static str,
+    ids: impl Iterator<Item = &'a String>
+) -> Result<HashSet<&'a str>, TerrainDefinitionError> {
+    let mut unique = HashSet::new();
+
+    for id in ids {
+        if !unique.insert(id.as_str()) {
+            return Err(TerrainDefinitionError::DuplicateId {
+                kind,
+                id: id.clone()
+            });
+        }
+    }
+
+    Ok(unique)
+}
+
+#[derive(Default)]
+pub struct TerrainDefinitionLoader;
+
+impl AssetLoader for TerrainDefinitionLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let definition = TerrainDefinition::from_bytes(bytes)?;
+
+            load_context.set_default_asset(LoadedAsset::new(definition));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["terrain.ron"]
+    }
+}
@@ -1,26 +1,20 @@
-use crate::terrain::biome::TileType;
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone)]
 pub struct Foliage {
+    pub id: String,
     pub foliage_type: FoliageType,
     pub weight: u16,
     pub scale: f32,
+    /// Asset path of the foliage sprite.
+    pub sprite: String,
     pub spawns_on: Vec<usize>
 }
 
-#[derive(Clone, Copy, PartialEq)]
-pub enum FoliageType {
-    NONE,
-    CACTUS,
-    ROSE,
-    ROCK,
-}
+/// Index of a foliage type in the `BiomeHandle`, or `NONE` for tiles without foliage.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct FoliageType(pub u16);
 
-pub fn get_foliage_paths(foliage_type: FoliageType) -> &'static str {
-    match foliage_type {
-        FoliageType::CACTUS => {"sprites/foliage/cactus.png"}
-        FoliageType::NONE => {""}
-        FoliageType::ROSE => {"sprites/foliage/rose.png"}
-        FoliageType::ROCK => {"sprites/foliage/rock.png"}
-    }
-}
\ No newline at end of file
+impl FoliageType {
+    pub const NONE: FoliageType = FoliageType(u16::MAX);
+}
@@ -0,0 +1,137 @@
+use bevy::prelude::IVec2;
+use crate::terrain::biome::{BiomeHandle, WorldGenError};
+use crate::terrain::biome_map::BiomeMap;
+use crate::terrain::chunk::CHUNK_SIZE;
+use crate::terrain::definition::{TerrainDefinition, TerrainDefinitionError};
+use crate::terrain::foliage::FoliageType;
+use crate::terrain::noise::{NoiseSettings, get_noise};
+use crate::terrain::season::Season;
+use crate::terrain::seed::WorldSeed;
+use crate::terrain::tile::Tile;
+
+/// Default noise picking tiles from the biome tile tables.
+pub const TERRAIN_NOISE: NoiseSettings = NoiseSettings::new(0.7, 5);
+/// Default noise placing foliage, high frequency so foliage is scattered rather than clumped.
+pub const FOLIAGE_NOISE: NoiseSettings = NoiseSettings::new(5.0, 5);
+
+/// Generated contents of a chunk, without any of the state used to render it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ChunkData {
+    pub coordinate: IVec2,
+    pub tiles: [[Tile; CHUNK_SIZE]; CHUNK_SIZE],
+    pub foliage_type: [[FoliageType; CHUNK_SIZE]; CHUNK_SIZE],
+    /// Season of the biome handle the chunk was generated with.
+    pub season: Season
+}
+
+/// Generates terrain from a seed and a resolved terrain definition, independently of the
+/// app, so chunks can be generated by tools, tests and benchmarks.
+///
+/// Generation is deterministic, the same seed and definition always give the same chunk.
+#[derive(Clone)]
+pub struct WorldGenerator {
+    seed: WorldSeed,
+    biome_handle: BiomeHandle
+}
+
+impl WorldGenerator {
+    pub fn new(
+        seed: WorldSeed,
+        biome_handle: BiomeHandle
+    ) -> Self {
+        WorldGenerator {
+            seed,
+            biome_handle
+        }
+    }
+
+    /// Validate and resolve a terrain definition, generating in its default season.
+    pub fn from_definition(
+        seed: WorldSeed,
+        definition: &TerrainDefinition
+    ) -> Result<Self, TerrainDefinitionError> {
+        Ok(WorldGenerator::new(seed, BiomeHandle::from_definition(definition)?))
+    }
+
+    pub fn seed(&self) -> &WorldSeed {
+        &self.seed
+    }
+
+    pub fn biome_handle(&self) -> &BiomeHandle {
+        &self.biome_handle
+    }
+
+    /// Generate following chunks with the tiles, foliage and tints of a season.
+    pub fn set_season(
+        &mut self,
+        season: Season
+    ) {
+        self.biome_handle.set_season(season);
+    }
+
+    /// Generate a chunk, only failing when the biome handle is empty or holds biomes that
+    /// were not registered through `BiomeHandle::add_biome`.
+    pub fn generate_chunk(
+        &self,
+        coordinate: IVec2
+    ) -> Result<ChunkData, WorldGenError> {
+        let seed = &self.seed;
+        let biome_handle = &self.biome_handle;
+
+        // Init blocks for chunk mesh layer
+        let mut tiles: [[Tile; CHUNK_SIZE]; CHUNK_SIZE] = [[Tile::default(); CHUNK_SIZE]; CHUNK_SIZE];
+
+        // Get noise map for terrain type
+        let noise = terrain_noise(coordinate, seed, biome_handle);
+
+        // Get biome map, including the blend border around the chunk
+        let biome_map = BiomeMap::generate(coordinate, seed, biome_handle)?;
+
+        // Foliage array
+        let foliage_noise = foliage_noise(coordinate, seed, biome_handle);
+        let mut foliage_type: [[FoliageType; CHUNK_SIZE]; CHUNK_SIZE] = [[FoliageType::NONE; CHUNK_SIZE]; CHUNK_SIZE];
+
+        // Iterate over each tile in chunk
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                let biome_type = biome_map.blended_biome(x, y, seed);
+                let biome = biome_handle.get_biome(biome_type)?;
+                let tile_type = biome.get_tile_from_rng(noise[x][y])?;
+
+                // Set tile
+                tiles[x][y] = Tile {
+                    tile: tile_type,
+                    biome: biome_type
+                };
+
+                // Set foliage
+                foliage_type[x][y] = biome.get_foliage_from_rng(foliage_noise[x][y], tile_type)?;
+            }
+        }
+
+        Ok(ChunkData {
+            coordinate,
+            tiles,
+            foliage_type,
+            season: biome_handle.season
+        })
+    }
+}
+
+/// Noise picking the tile of each position from its biome's tile table.
+pub(crate) fn terrain_noise(
+    coordinate: IVec2,
+    seed: &WorldSeed,
+    biome_handle: &BiomeHandle
+) -> [[f32; CHUNK_SIZE]; CHUNK_SIZE] {
+    get_noise(coordinate.as_vec2(), seed.terrain(), &biome_handle.noise.terrain)
+}
+
+/// Noise placing foliage.
+pub(crate) fn foliage_noise(
+    coordinate: IVec2,
+    seed: &WorldSeed,
+    biome_handle: &BiomeHandle
+) -> [[f32; CHUNK_SIZE]; CHUNK_SIZE] {
+    get_noise(coordinate.as_vec2(), seed.foliage(), &biome_handle.noise.foliage)
+}
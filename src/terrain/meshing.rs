@@ -1,9 +1,178 @@
+use std::env;
 use bevy::{
     prelude::*,
 };
 use bevy::render::mesh::{Indices, PrimitiveTopology};
-use crate::terrain::chunk::{TILE_SIZE};
-use crate::terrain::terrain::TEXTURE_DIMENSION;
+use crate::terrain::atlas::TileAtlas;
+use crate::terrain::chunk::{CHUNK_SIZE, TILE_SIZE};
+use crate::terrain::biome::BiomeType;
+use crate::terrain::chunk_material::{ATTRIBUTE_ANIMATION, ATTRIBUTE_ATLAS_RECT, ATTRIBUTE_BIOME, ATTRIBUTE_TINT, ATTRIBUTE_VARIANT, ATTRIBUTE_VARIANT_CELLS};
+use crate::terrain::definition::{MAX_VARIANT_CELLS, TileAnimation, TileVariantsDefinition};
+
+/// Environment variable read for the meshing mode when none is given on the command line.
+pub const MESHING_ENV_VAR: &str = "CLI_MATE_MESHING";
+
+/// Command line flag for the meshing mode, i.e. `--meshing greedy`.
+pub const MESHING_ARG: &str = "--meshing";
+
+/// How chunk meshes are built from their tiles.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum MeshingMode {
+    /// One quad per tile.
+    #[default]
+    Tiles,
+    /// Rectangles of tiles that look the same are merged into a single quad, the
+    /// `ChunkMaterial` repeats the atlas cell across the quad.
+    Greedy
+}
+
+impl MeshingMode {
+    /// Read the mode from the `--meshing` argument, falling back to the `CLI_MATE_MESHING`
+    /// environment variable and then to one quad per tile.
+    pub fn from_env() -> Self {
+        let arg = env::args()
+            .skip_while(|arg| arg != MESHING_ARG)
+            .nth(1);
+
+        match arg.or_else(|| env::var(MESHING_ENV_VAR).ok()) {
+            Some(value) => MeshingMode::parse(&value).unwrap_or_else(|| {
+                warn!("Invalid meshing mode '{}', using tiles", value);
+                MeshingMode::default()
+            }),
+            None => MeshingMode::default()
+        }
+    }
+
+    pub fn parse(
+        value: &str
+    ) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "tiles" => Some(MeshingMode::Tiles),
+            "greedy" => Some(MeshingMode::Greedy),
+            _ => None
+        }
+    }
+}
+
+/// How a tile is drawn, tiles only merge into a single quad when they look the same.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TileAppearance {
+    /// Atlas cell of the first animation frame.
+    pub cell: usize,
+    pub tint: Color,
+    pub animation: Option<TileAnimation>,
+    pub variants: TileVariants,
+    /// Biome whose ground weather the tile shows.
+    pub biome: BiomeType
+}
+
+impl Default for TileAppearance {
+    fn default() -> Self {
+        TileAppearance {
+            cell: 0,
+            tint: Color::WHITE,
+            animation: None,
+            variants: TileVariants::default(),
+            biome: BiomeType::default()
+        }
+    }
+}
+
+/// Looks a tile can take, picked per tile by `ChunkMaterial` from a hash of its world
+/// position so quads merged across several tiles still vary tile by tile.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TileVariants {
+    /// Atlas cells used alongside `TileAppearance::cell`, the first `cell_count` are set.
+    pub cells: [usize; MAX_VARIANT_CELLS],
+    pub cell_count: usize,
+    pub rotate: bool,
+    pub flip: bool,
+    /// Seed of the hash, `WorldSeed::variant`.
+    pub seed: u32
+}
+
+impl TileVariants {
+    pub fn from_definition(
+        definition: &TileVariantsDefinition,
+        seed: u32
+    ) -> Self {
+        let mut cells = [0; MAX_VARIANT_CELLS];
+        let cell_count = definition.cells.len().min(MAX_VARIANT_CELLS);
+        cells[..cell_count].copy_from_slice(&definition.cells[..cell_count]);
+
+        TileVariants {
+            cells,
+            cell_count,
+            rotate: definition.rotate,
+            flip: definition.flip,
+            seed
+        }
+    }
+
+    /// Rotate and flip in the lowest two bits and the number of extra cells above them,
+    /// see `ATTRIBUTE_VARIANT`.
+    fn options(
+        &self
+    ) -> u32 {
+        self.rotate as u32 | (self.flip as u32) << 1 | (self.cell_count as u32) << 2
+    }
+}
+
+/// Rectangle of tiles with the same value, in tiles from the bottom left of the chunk.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TileQuad<T> {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub tile: T
+}
+
+/// Merge equal tiles of a chunk into as few rectangles as possible, growing each rectangle
+/// along x first and then along y.
+pub fn greedy_quads<T: Copy + PartialEq>(
+    tiles: &[[T; CHUNK_SIZE]; CHUNK_SIZE]
+) -> Vec<TileQuad<T>> {
+    let mut merged = [[false; CHUNK_SIZE]; CHUNK_SIZE];
+    let mut quads = Vec::new();
+
+    for y in 0..CHUNK_SIZE {
+        for x in 0..CHUNK_SIZE {
+            if merged[x][y] {
+                continue;
+            }
+
+            let tile = tiles[x][y];
+            let mergeable = |x: usize, y: usize| !merged[x][y] && tiles[x][y] == tile;
+
+            let mut width = 1;
+            while x + width < CHUNK_SIZE && mergeable(x + width, y) {
+                width += 1;
+            }
+
+            let mut height = 1;
+            while y + height < CHUNK_SIZE && (x..x + width).all(|x| mergeable(x, y + height)) {
+                height += 1;
+            }
+
+            for column in merged.iter_mut().skip(x).take(width) {
+                for merged_tile in column.iter_mut().skip(y).take(height) {
+                    *merged_tile = true;
+                }
+            }
+
+            quads.push(TileQuad {
+                x,
+                y,
+                width,
+                height,
+                tile
+            });
+        }
+    }
+
+    quads
+}
 
 #[derive(Default)]
 pub struct ChunkTileMapBuilder {
@@ -11,11 +180,16 @@ pub struct ChunkTileMapBuilder {
     triangles: Vec<u32>,
     normals: Vec<[f32; 3]>,
     uvs: Vec<[f32; 2]>,
+    atlas_rects: Vec<[f32; 4]>,
+    tints: Vec<[f32; 4]>,
+    animations: Vec<[f32; 4]>,
+    variants: Vec<[u32; 2]>,
+    variant_cells: Vec<[f32; 4]>,
+    biomes: Vec<u32>,
     face_count: u32
 }
 
 impl ChunkTileMapBuilder {
-    #[allow(dead_code)]
     pub fn new() -> Self {
         Self::default()
     }
@@ -27,24 +201,78 @@ impl ChunkTileMapBuilder {
         self.triangles.clear();
         self.normals.clear();
         self.uvs.clear();
+        self.atlas_rects.clear();
+        self.tints.clear();
+        self.animations.clear();
+        self.variants.clear();
+        self.variant_cells.clear();
+        self.biomes.clear();
         self.face_count = 0;
     }
 
-    /// Tiles are numbered from bottom left to top right starting with rows first.
+    /// Atlas cells are numbered from top left to bottom right starting with rows first.
     ///
     /// i.e.
     ///
+    /// ```text
+    /// 0    , 1    , 2     ...
+    /// #      #      #     ...
+    /// #      #      #     ...
+    /// N - 2, N - 1, N     ...
     /// ```
-    /// ... N - 2, N - 1, N
-    /// ... #      #      #
-    /// ... #      #      #
-    /// ... 0    , 1    , 2
-    /// ```
-    pub fn add_tile(&mut self, tile_offset: Vec2, tile_type: usize) {
-        let bl = [tile_offset.x, tile_offset.y, 0.0];
-        let tl = [tile_offset.x, tile_offset.y + TILE_SIZE, 0.0];
-        let br = [tile_offset.x + TILE_SIZE, tile_offset.y, 0.0];
-        let tr = [tile_offset.x + TILE_SIZE, tile_offset.y + TILE_SIZE, 0.0];
+    pub fn add_tile(&mut self, tile_offset: Vec2, appearance: &TileAppearance, tile_atlas: &TileAtlas) {
+        self.add_tiled_quad(tile_offset, Vec2::ONE, appearance, tile_atlas);
+    }
+
+    /// Add a quad of `size` tiles repeating the atlas cell of `appearance`, the uvs count
+    /// tiles across the quad for `ChunkMaterial` to wrap.
+    pub fn add_tiled_quad(&mut self, tile_offset: Vec2, size: Vec2, appearance: &TileAppearance, tile_atlas: &TileAtlas) {
+        self.add_quad(tile_offset, size * TILE_SIZE);
+
+        // bl, tl, br, tr, v goes down the atlas while y goes up
+        let uvs = [
+            [0.0, size.y],
+            [0.0, 0.0],
+            [size.x, size.y],
+            [size.x, 0.0],
+        ];
+
+        self.uvs.extend_from_slice(&uvs);
+
+        let rect = tile_atlas.cell_rect(appearance.cell);
+
+        // Frames are a constant uv step apart as long as the offset keeps the column
+        let animation = match appearance.animation {
+            Some(animation) => {
+                let frame_step = tile_atlas.cell_rect(appearance.cell + animation.frame_offset).min - rect.min;
+                [animation.frames as f32, animation.frames_per_second, frame_step.x, frame_step.y]
+            }
+            None => [1.0, 0.0, 0.0, 0.0]
+        };
+
+        let variants = appearance.variants;
+        let variant_cell = |index: usize| if index < variants.cell_count {
+            tile_atlas.cell_rect(variants.cells[index]).min - rect.min
+        } else {
+            Vec2::ZERO
+        };
+        let (first_cell, second_cell) = (variant_cell(0), variant_cell(1));
+
+        for _ in 0..4 {
+            self.atlas_rects.push([rect.min.x, rect.min.y, rect.width(), rect.height()]);
+            self.tints.push(appearance.tint.as_rgba_f32());
+            self.animations.push(animation);
+            self.variants.push([variants.options(), variants.seed]);
+            self.variant_cells.push([first_cell.x, first_cell.y, second_cell.x, second_cell.y]);
+            self.biomes.push(appearance.biome.0 as u32);
+        }
+    }
+
+    fn add_quad(&mut self, offset: Vec2, size: Vec2) {
+        let bl = [offset.x, offset.y, 0.0];
+        let tl = [offset.x, offset.y + size.y, 0.0];
+        let br = [offset.x + size.x, offset.y, 0.0];
+        let tr = [offset.x + size.x, offset.y + size.y, 0.0];
         let vertices = [bl, tl, br, tr];
 
         self.vertices.extend_from_slice(&vertices);
@@ -61,20 +289,6 @@ impl ChunkTileMapBuilder {
             self.normals.push([0.0, 0.0, 1.0]);
         }
 
-        let row = (tile_type as f32 / TEXTURE_DIMENSION).floor();
-        let col = (tile_type as f32 % TEXTURE_DIMENSION).floor();
-        let side_size = 1.0 / TEXTURE_DIMENSION;
-
-        // bl, tl, br, tr
-        let uvs = [
-            [col * side_size + side_size, row * side_size + side_size],
-            [col * side_size, row * side_size],
-            [col * side_size, row * side_size + side_size],
-            [col * side_size + side_size, row * side_size],
-        ];
-
-        self.uvs.extend_from_slice(&uvs);
-
         self.face_count += 1;
     }
 
@@ -85,6 +299,12 @@ impl ChunkTileMapBuilder {
         msh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.vertices.clone());
         msh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals.clone());
         msh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs.clone());
+        msh.insert_attribute(ATTRIBUTE_ATLAS_RECT, self.atlas_rects.clone());
+        msh.insert_attribute(ATTRIBUTE_TINT, self.tints.clone());
+        msh.insert_attribute(ATTRIBUTE_ANIMATION, self.animations.clone());
+        msh.insert_attribute(ATTRIBUTE_VARIANT, self.variants.clone());
+        msh.insert_attribute(ATTRIBUTE_VARIANT_CELLS, self.variant_cells.clone());
+        msh.insert_attribute(ATTRIBUTE_BIOME, self.biomes.clone());
 
         msh.set_indices(Some(Indices::U32(self.triangles.clone())));
         msh
@@ -1,45 +1,380 @@
+use std::sync::Arc;
 use bevy::prelude::*;
-use noise::{NoiseFn, Perlin};
+use noise::{NoiseFn, OpenSimplex, Perlin, Simplex, Value, Worley};
+use serde::Deserialize;
 use crate::terrain::chunk::CHUNK_SIZE;
+use crate::terrain::definition::NoiseDefinition;
 
+/// Number of values sampled to estimate the distribution of an equalised layer.
+const EQUALIZE_SAMPLES: usize = 16384;
+/// Side of the area the equalisation samples are spread over, in features of the first
+/// octave, large enough to cover every octave evenly.
+const EQUALIZE_AREA: f32 = 4096.0;
+/// Seed of the noise the distribution is measured on, the distribution of a layer does not
+/// depend on its seed.
+const EQUALIZE_SEED: u32 = 0;
+
+/// Salts of the seeds of the two fields offsetting the sample position of a warped layer.
+const WARP_X_SALT: u32 = 0x57A3_91C5;
+const WARP_Y_SALT: u32 = 0x2B8E_D40F;
+
+/// Largest `f32` below 1.0, equalised values stay in [0, 1) like `position_hash`.
+const BELOW_ONE: f32 = 1.0 - f32::EPSILON / 2.0;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum NoiseAlgorithm {
+    #[default]
+    Perlin,
+    Simplex,
+    OpenSimplex,
+    /// Cellular noise, each cell of a Voronoi diagram has a single value.
+    Worley,
+    /// Interpolated random values on a grid, blockier than gradient noise.
+    Value
+}
+
+/// Offsets the position a layer is sampled at by a second pair of noise fields, bending
+/// the straight edges and blobs of plain fractal noise.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct DomainWarp {
+    /// Largest offset, in chunks.
+    pub strength: f32,
+    /// Features per chunk of the offset fields.
+    pub frequency: f32
+}
+
+/// Fractal noise made of `octaves` layers of an algorithm, each octave's frequency is
+/// multiplied by `lacunarity` and its amplitude by `persistence`.
+///
+/// Fields left out of a definition file default to Perlin noise with the persistence of
+/// 0.5 and lacunarity of 2 all layers used before they were configurable.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct NoiseSettings {
+    #[serde(default)]
+    pub algorithm: NoiseAlgorithm,
+    /// Features per chunk of the first octave.
+    pub frequency: f32,
+    pub octaves: u32,
+    #[serde(default = "default_persistence")]
+    pub persistence: f32,
+    #[serde(default = "default_lacunarity")]
+    pub lacunarity: f32,
+    /// Added to the position before sampling, in chunks.
+    #[serde(default)]
+    pub offset: [f32; 2],
+    #[serde(default)]
+    pub warp: Option<DomainWarp>,
+    /// Remap the values so they are uniformly distributed in [0, 1). Fractal noise
+    /// clusters around 0.5, so without it the first and last entries of a weighted table
+    /// picked from the noise show up far less often than their weights say.
+    #[serde(default)]
+    pub equalize: bool
+}
+
+fn default_persistence() -> f32 {
+    0.5
+}
+
+fn default_lacunarity() -> f32 {
+    2.0
+}
+
+impl NoiseSettings {
+    /// Perlin noise with the default persistence and lacunarity, without warp or
+    /// equalisation.
+    pub const fn new(
+        frequency: f32,
+        octaves: u32
+    ) -> Self {
+        NoiseSettings {
+            algorithm: NoiseAlgorithm::Perlin,
+            frequency,
+            octaves,
+            persistence: 0.5,
+            lacunarity: 2.0,
+            offset: [0.0, 0.0],
+            warp: None,
+            equalize: false
+        }
+    }
+
+    /// Reason the settings can't produce noise in [0, 1], if any.
+    pub fn validate(
+        &self
+    ) -> Result<(), String> {
+        if self.octaves == 0 {
+            return Err("octaves must be at least 1".to_string());
+        }
+
+        let positive = [
+            ("frequency", self.frequency),
+            ("persistence", self.persistence),
+            ("lacunarity", self.lacunarity)
+        ];
+
+        for (name, value) in positive {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(format!("{} must be positive, found {}", name, value));
+            }
+        }
+
+        if !self.offset.iter().all(|offset| offset.is_finite()) {
+            return Err("offset must be finite".to_string());
+        }
+
+        if let Some(warp) = self.warp {
+            if !(warp.strength.is_finite() && warp.strength >= 0.0) {
+                return Err(format!("warp strength must not be negative, found {}", warp.strength));
+            }
+
+            if !(warp.frequency.is_finite() && warp.frequency > 0.0) {
+                return Err(format!("warp frequency must be positive, found {}", warp.frequency));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Noise settings along with the distribution equalised layers are remapped by, measured
+/// once when the terrain definition is resolved rather than for every chunk.
+#[derive(Clone, Debug)]
+pub struct NoiseLayer {
+    pub settings: NoiseSettings,
+    /// Sorted sample of the layer's raw values when it is equalised.
+    distribution: Option<Arc<[f32]>>
+}
+
+impl NoiseLayer {
+    pub fn new(
+        settings: NoiseSettings
+    ) -> Self {
+        let distribution = settings.equalize.then(|| {
+            let source = NoiseSource::new(settings.algorithm, EQUALIZE_SEED);
+            let area = EQUALIZE_AREA / settings.frequency;
+
+            let mut samples: Vec<f32> = (0..EQUALIZE_SAMPLES)
+                .map(|index| {
+                    let index = IVec2::new(index as i32, 0);
+                    let point = Vec2::new(position_hash(1, index), position_hash(2, index)) * area;
+
+                    fractal_noise(&source, &settings, point)
+                })
+                .collect();
+
+            samples.sort_by(f32::total_cmp);
+            samples.into()
+        });
+
+        NoiseLayer {
+            settings,
+            distribution
+        }
+    }
+
+    /// Sample the layer with a seed, creating the noise functions once for many points.
+    pub fn sampler(
+        &self,
+        seed: u32
+    ) -> NoiseSampler<'_> {
+        NoiseSampler {
+            layer: self,
+            source: NoiseSource::new(self.settings.algorithm, seed),
+            warp: self.settings.warp
+                .map(|_| [Perlin::new(seed ^ WARP_X_SALT), Perlin::new(seed ^ WARP_Y_SALT)])
+        }
+    }
+}
+
+/// Seeded noise functions of a `NoiseLayer`.
+pub struct NoiseSampler<'a> {
+    layer: &'a NoiseLayer,
+    source: NoiseSource,
+    warp: Option<[Perlin; 2]>
+}
+
+impl NoiseSampler<'_> {
+    /// Value in [0, 1] at a point given in chunk units, in [0, 1) and uniformly
+    /// distributed when the layer is equalised.
+    pub fn sample(
+        &self,
+        point: Vec2
+    ) -> f32 {
+        let settings = &self.layer.settings;
+        let mut point = point + Vec2::from(settings.offset);
+
+        if let (Some(warp), Some([warp_x, warp_y])) = (settings.warp, &self.warp) {
+            let warp_point = [
+                point.x as f64 * warp.frequency as f64,
+                point.y as f64 * warp.frequency as f64
+            ];
+
+            point += Vec2::new(warp_x.get(warp_point) as f32, warp_y.get(warp_point) as f32) * warp.strength;
+        }
+
+        let value = fractal_noise(&self.source, settings, point);
+
+        match &self.layer.distribution {
+            Some(distribution) => equalize(distribution, value),
+            None => value
+        }
+    }
+}
+
+/// Resolved layers of a `NoiseDefinition`, see it for what each layer is used for.
+#[derive(Clone, Debug)]
+pub struct NoiseLayers {
+    pub terrain: NoiseLayer,
+    pub foliage: NoiseLayer,
+    pub biome: NoiseLayer,
+    pub temperature: NoiseLayer,
+    pub moisture: NoiseLayer,
+    pub elevation: NoiseLayer
+}
+
+impl From<&NoiseDefinition> for NoiseLayers {
+    fn from(definition: &NoiseDefinition) -> Self {
+        NoiseLayers {
+            terrain: NoiseLayer::new(definition.terrain),
+            foliage: NoiseLayer::new(definition.foliage),
+            biome: NoiseLayer::new(definition.biome),
+            temperature: NoiseLayer::new(definition.temperature),
+            moisture: NoiseLayer::new(definition.moisture),
+            elevation: NoiseLayer::new(definition.elevation)
+        }
+    }
+}
+
+impl Default for NoiseLayers {
+    fn default() -> Self {
+        NoiseLayers::from(&NoiseDefinition::default())
+    }
+}
+
+enum NoiseSource {
+    Perlin(Perlin),
+    Simplex(Simplex),
+    OpenSimplex(OpenSimplex),
+    Worley(Worley),
+    Value(Value)
+}
+
+impl NoiseSource {
+    fn new(
+        algorithm: NoiseAlgorithm,
+        seed: u32
+    ) -> Self {
+        match algorithm {
+            NoiseAlgorithm::Perlin => NoiseSource::Perlin(Perlin::new(seed)),
+            NoiseAlgorithm::Simplex => NoiseSource::Simplex(Simplex::new(seed)),
+            NoiseAlgorithm::OpenSimplex => NoiseSource::OpenSimplex(OpenSimplex::new(seed)),
+            NoiseAlgorithm::Worley => NoiseSource::Worley(Worley::new(seed)),
+            NoiseAlgorithm::Value => NoiseSource::Value(Value::new(seed))
+        }
+    }
+
+    /// Value in roughly [-1, 1].
+    fn get(
+        &self,
+        point: [f64; 2]
+    ) -> f64 {
+        match self {
+            NoiseSource::Perlin(noise) => noise.get(point),
+            NoiseSource::Simplex(noise) => noise.get(point),
+            NoiseSource::OpenSimplex(noise) => noise.get(point),
+            NoiseSource::Worley(noise) => noise.get(point),
+            NoiseSource::Value(noise) => noise.get(point)
+        }
+    }
+}
+
+/// Octaves of a noise source at a point given in chunk units, normalised by the sum of
+/// the amplitudes and clamped to [0, 1].
+fn fractal_noise(
+    source: &NoiseSource,
+    settings: &NoiseSettings,
+    point: Vec2
+) -> f32 {
+    let point = point * settings.frequency;
+    let persistence = settings.persistence as f64;
+    let lacunarity = settings.lacunarity as f64;
+
+    let mut val = 0.0;
+    let mut den = 0.0;
+
+    for i in 0..settings.octaves as i32 {
+        let amplitude = persistence.powi(i);
+        let scale = lacunarity.powi(i);
+
+        val += amplitude * 0.5
+            * (1.0 + source.get(
+            [
+                point.x as f64 * scale,
+                point.y as f64 * scale
+            ])
+        );
+
+        den += amplitude;
+    }
+
+    (val / den).clamp(0.0, 1.0) as f32
+}
+
+/// Fraction of the distribution's samples below a value, interpolated between samples.
+fn equalize(
+    distribution: &[f32],
+    value: f32
+) -> f32 {
+    let upper = distribution.partition_point(|sample| *sample <= value);
+
+    if upper == 0 {
+        return 0.0;
+    }
+
+    if upper == distribution.len() {
+        return BELOW_ONE;
+    }
+
+    let (low, high) = (distribution[upper - 1], distribution[upper]);
+    let t = if high > low { (value - low) / (high - low) } else { 0.0 };
+
+    (((upper - 1) as f32 + t) / (distribution.len() - 1) as f32).min(BELOW_ONE)
+}
+
+/// Noise of every tile of a chunk.
 pub fn get_noise(
     coordinate: Vec2,
     seed: u32,
-    frequency: f32,
-    octaves: i32
+    layer: &NoiseLayer
 ) -> [[f32; CHUNK_SIZE]; CHUNK_SIZE] {
-    let prng = Perlin::new(seed);
+    let sampler = layer.sampler(seed);
 
     let mut noise = [[0.0; CHUNK_SIZE]; CHUNK_SIZE];
 
     for x in 0..CHUNK_SIZE {
         for y in 0..CHUNK_SIZE {
-            let point =
-                (Vec2::new(x as f32, y as f32) / CHUNK_SIZE as f32 + coordinate)
-                    * frequency;
+            let point = Vec2::new(x as f32, y as f32) / CHUNK_SIZE as f32 + coordinate;
 
-            let mut val = 0.0;
-            let mut den = 0.0;
-
-            for i in 0..octaves {
-                let pow_val = (2.0 as f64).powi(i);
-
-                val += 0.5 / pow_val
-                    * (1.0 + prng.get(
-                    [
-                        point.x as f64 * pow_val,
-                        point.y as f64 * pow_val
-                    ])
-                );
-
-                den += 1.0 / pow_val;
-            }
-
-            val /= den;
-
-            noise[x][y] = val.abs() as f32;
+            noise[x][y] = sampler.sample(point);
         }
     }
 
     noise
-}
\ No newline at end of file
+}
+
+/// Uniformly distributed value in [0, 1) hashed from a world tile position, unlike
+/// `get_noise` neighbouring tiles are uncorrelated.
+pub fn position_hash(
+    seed: u32,
+    position: IVec2
+) -> f32 {
+    let mut z = (position.x as u32 as u64 | (position.y as u32 as u64) << 32)
+        ^ (seed as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+
+    // Top 24 bits, exactly representable as an f32 below 1.0
+    (z >> 40) as f32 / (1u64 << 24) as f32
+}
@@ -0,0 +1,272 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::terrain::biome::BiomeType;
+use crate::terrain::chunk::CHUNK_SIZE;
+use crate::terrain::foliage::FoliageType;
+use crate::terrain::tile::Tile;
+
+/// Number of chunks along each side of a region file.
+pub const REGION_SIZE: i32 = 16;
+
+pub const REGION_FORMAT_VERSION: u32 = 2;
+
+/// Tiles of version 1 regions, which stored tiles by atlas cell before they came from the
+/// terrain definition.
+const V1_TILE_IDS: [(usize, &str); 5] = [(0, "dirt"), (1, "grass"), (2, "stone"), (3, "sand"), (8, "water")];
+/// Biomes of version 1 regions, in the order of the old `BiomeType` enum.
+const V1_BIOME_IDS: [&str; 5] = ["plains", "desert", "grassland", "beach", "ocean"];
+/// Foliage of version 1 regions, in the order of the old `FoliageType` enum after `NONE`.
+const V1_FOLIAGE_IDS: [&str; 3] = ["cactus", "rose", "rock"];
+
+pub const DEFAULT_REGION_DIRECTORY: &str = "saves/world/regions";
+
+/// Saved state of a chunk that has been modified since it was generated.
+///
+/// Tiles, biomes and foliage are saved as indices into the string id lists, so saves stay
+/// valid when the terrain definition is reordered.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChunkSave {
+    pub tile_ids: Vec<String>,
+    pub biome_ids: Vec<String>,
+    pub foliage_ids: Vec<String>,
+    pub tiles: [[Tile; CHUNK_SIZE]; CHUNK_SIZE],
+    pub foliage_type: [[FoliageType; CHUNK_SIZE]; CHUNK_SIZE],
+}
+
+/// Contents of a single region file, chunks are keyed by their index inside the region.
+#[derive(Serialize, Deserialize)]
+struct RegionFile {
+    version: u32,
+    chunks: BTreeMap<u16, ChunkSave>
+}
+
+/// Region file layout of version 1, biomes and foliage were enums serialized by variant
+/// index.
+#[derive(Deserialize)]
+struct RegionFileV1 {
+    chunks: BTreeMap<u16, ChunkSaveV1>
+}
+
+#[derive(Deserialize)]
+struct ChunkSaveV1 {
+    tiles: [[TileV1; CHUNK_SIZE]; CHUNK_SIZE],
+    foliage_type: [[u32; CHUNK_SIZE]; CHUNK_SIZE]
+}
+
+#[derive(Deserialize, Clone, Copy)]
+struct TileV1 {
+    tile: usize,
+    biome: u32
+}
+
+impl Default for RegionFile {
+    fn default() -> Self {
+        RegionFile {
+            version: REGION_FORMAT_VERSION,
+            chunks: BTreeMap::new()
+        }
+    }
+}
+
+/// Reads and writes modified chunks to region files, each holding
+/// `REGION_SIZE` x `REGION_SIZE` chunks.
+#[derive(Resource, Clone)]
+pub struct RegionStore {
+    pub directory: PathBuf
+}
+
+impl RegionStore {
+    pub fn new(
+        directory: impl Into<PathBuf>
+    ) -> Self {
+        RegionStore {
+            directory: directory.into()
+        }
+    }
+
+    pub fn region_coordinate(
+        chunk_coordinate: IVec2
+    ) -> IVec2 {
+        IVec2::new(
+            chunk_coordinate.x.div_euclid(REGION_SIZE),
+            chunk_coordinate.y.div_euclid(REGION_SIZE))
+    }
+
+    fn chunk_index(
+        chunk_coordinate: IVec2
+    ) -> u16 {
+        let x = chunk_coordinate.x.rem_euclid(REGION_SIZE);
+        let y = chunk_coordinate.y.rem_euclid(REGION_SIZE);
+
+        (x + y * REGION_SIZE) as u16
+    }
+
+    pub fn region_path(
+        &self,
+        region_coordinate: IVec2
+    ) -> PathBuf {
+        self.directory.join(format!("r.{}.{}.bin", region_coordinate.x, region_coordinate.y))
+    }
+
+    /// Load the saved chunk, if the chunk has been saved before.
+    pub fn load_chunk(
+        &self,
+        chunk_coordinate: IVec2
+    ) -> io::Result<Option<ChunkSave>> {
+        let path = self.region_path(RegionStore::region_coordinate(chunk_coordinate));
+
+        let mut region = read_region(&path)?;
+
+        Ok(region.chunks.remove(&RegionStore::chunk_index(chunk_coordinate)))
+    }
+
+    /// Write the chunk into its region file, replacing any previous save.
+    pub fn save_chunk(
+        &self,
+        chunk_coordinate: IVec2,
+        chunk_save: ChunkSave
+    ) -> io::Result<()> {
+        self.save_chunks(vec![(chunk_coordinate, chunk_save)])
+    }
+
+    /// Write several chunks, reading and writing each affected region file once.
+    pub fn save_chunks(
+        &self,
+        chunks: Vec<(IVec2, ChunkSave)>
+    ) -> io::Result<()> {
+        let mut by_region: BTreeMap<(i32, i32), Vec<(IVec2, ChunkSave)>> = BTreeMap::new();
+
+        for (chunk_coordinate, chunk_save) in chunks {
+            let region = RegionStore::region_coordinate(chunk_coordinate);
+            by_region.entry((region.x, region.y)).or_default().push((chunk_coordinate, chunk_save));
+        }
+
+        fs::create_dir_all(&self.directory)?;
+
+        for ((x, y), chunks) in by_region {
+            let path = self.region_path(IVec2::new(x, y));
+            let mut region = match read_region(&path) {
+                Ok(region) => region,
+                Err(error) if error.kind() == io::ErrorKind::InvalidData => set_aside_region(&path, &error)?,
+                Err(error) => return Err(error)
+            };
+
+            for (chunk_coordinate, chunk_save) in chunks {
+                region.chunks.insert(RegionStore::chunk_index(chunk_coordinate), chunk_save);
+            }
+
+            write_region(&path, &region)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for RegionStore {
+    fn default() -> Self {
+        RegionStore::new(DEFAULT_REGION_DIRECTORY)
+    }
+}
+
+fn read_region(
+    path: &Path
+) -> io::Result<RegionFile> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(RegionFile::default()),
+        Err(error) => return Err(error)
+    };
+
+    let invalid_data = |error| io::Error::new(io::ErrorKind::InvalidData, error);
+
+    // Every version starts with the version number
+    let version: u32 = bincode::deserialize(&bytes).map_err(invalid_data)?;
+
+    match version {
+        REGION_FORMAT_VERSION => bincode::deserialize(&bytes).map_err(invalid_data),
+        1 => {
+            let (_, region): (u32, RegionFileV1) = bincode::deserialize(&bytes).map_err(invalid_data)?;
+            Ok(migrate_v1(region))
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Unsupported region format version {}", version)))
+    }
+}
+
+/// Give version 1 chunks the id tables of version 2. Version 1 tiles were atlas cells,
+/// unknown cells are mapped past the end of the tile ids so they fall back like unknown ids.
+fn migrate_v1(
+    region: RegionFileV1
+) -> RegionFile {
+    let chunks = region.chunks.into_iter()
+        .map(|(index, chunk)| {
+            let mut tiles = [[Tile::default(); CHUNK_SIZE]; CHUNK_SIZE];
+            let mut foliage_type = [[FoliageType::NONE; CHUNK_SIZE]; CHUNK_SIZE];
+
+            for x in 0..CHUNK_SIZE {
+                for y in 0..CHUNK_SIZE {
+                    let tile = chunk.tiles[x][y];
+
+                    tiles[x][y] = Tile {
+                        tile: V1_TILE_IDS.iter()
+                            .position(|(cell, _)| *cell == tile.tile)
+                            .unwrap_or(V1_TILE_IDS.len()),
+                        biome: BiomeType(tile.biome as u16)
+                    };
+
+                    if let Some(foliage) = chunk.foliage_type[x][y].checked_sub(1) {
+                        foliage_type[x][y] = FoliageType(foliage as u16);
+                    }
+                }
+            }
+
+            (index, ChunkSave {
+                tile_ids: V1_TILE_IDS.iter().map(|(_, id)| id.to_string()).collect(),
+                biome_ids: V1_BIOME_IDS.iter().map(|id| id.to_string()).collect(),
+                foliage_ids: V1_FOLIAGE_IDS.iter().map(|id| id.to_string()).collect(),
+                tiles,
+                foliage_type
+            })
+        })
+        .collect();
+
+    RegionFile {
+        version: REGION_FORMAT_VERSION,
+        chunks
+    }
+}
+
+/// Rename a region file that can't be read to `*.corrupt`, so its chunks can still be
+/// recovered by hand, and start a new region in its place. Without this every later save
+/// into the region would fail.
+fn set_aside_region(
+    path: &Path,
+    error: &io::Error
+) -> io::Result<RegionFile> {
+    let corrupt_path = path.with_extension("bin.corrupt");
+    fs::rename(path, &corrupt_path)?;
+
+    warn!("Region file {} could not be read ({}), moved it to {} and started a new region",
+          path.display(), error, corrupt_path.display());
+
+    Ok(RegionFile::default())
+}
+
+/// Write to a temporary file first so a region being read by a generation task is never
+/// seen half written.
+fn write_region(
+    path: &Path,
+    region: &RegionFile
+) -> io::Result<()> {
+    let bytes = bincode::serialize(region)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+    let temp_path = path.with_extension("bin.tmp");
+    fs::write(&temp_path, bytes)?;
+    fs::rename(temp_path, path)
+}
@@ -0,0 +1,169 @@
+use std::sync::Arc;
+use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use bevy::utils::HashMap;
+use futures_lite::future;
+use serde::Deserialize;
+use crate::clock::WorldClock;
+use crate::terrain::biome::{BiomeHandle, WorldGenError};
+use crate::terrain::chunk::Chunk;
+use crate::terrain::chunk_handler::{ChunkHandler, update_chunks};
+use crate::terrain::seed::WorldSeed;
+use crate::terrain::tile::TileChanged;
+
+/// Days in each season of the `WorldClock`.
+pub const DAYS_PER_SEASON: u32 = 3;
+
+pub struct SeasonPlugin;
+impl Plugin for SeasonPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SeasonalUpdates>()
+            .add_system(update_season.before(update_chunks))
+            .add_system(queue_seasonal_updates.after(update_season))
+            .add_system(apply_seasonal_updates);
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Season {
+    #[default]
+    Spring,
+    Summer,
+    Autumn,
+    Winter
+}
+
+impl Season {
+    pub const ALL: [Season; 4] = [Season::Spring, Season::Summer, Season::Autumn, Season::Winter];
+
+    /// Season of a day of the world clock, starting in spring.
+    pub fn from_day(
+        day: u32
+    ) -> Self {
+        Season::ALL[(day / DAYS_PER_SEASON) as usize % Season::ALL.len()]
+    }
+
+    pub fn parse(
+        value: &str
+    ) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "spring" => Some(Season::Spring),
+            "summer" => Some(Season::Summer),
+            "autumn" => Some(Season::Autumn),
+            "winter" => Some(Season::Winter),
+            _ => None
+        }
+    }
+}
+
+/// Result of re-evaluating a chunk for a new season.
+pub struct SeasonalUpdate {
+    pub season: Season,
+    /// Tiles and foliage to change, `old_tile` and `old_foliage` are what the chunk held
+    /// when it was evaluated.
+    pub changes: Vec<TileChanged>,
+    /// Whether the tint of any tinted tile changed, so the chunk needs a remesh even when
+    /// no tile changed.
+    pub tint_changed: bool
+}
+
+/// Chunks being re-evaluated for the current season on the async compute task pool.
+#[derive(Resource, Default)]
+pub struct SeasonalUpdates {
+    pub tasks: HashMap<IVec2, Task<Result<SeasonalUpdate, WorldGenError>>>
+}
+
+/// Switch the biome handle to the season of the world clock.
+fn update_season(
+    world_clock: Res<WorldClock>,
+    mut biome_handle: ResMut<BiomeHandle>
+) {
+    let season = Season::from_day(world_clock.day);
+
+    if biome_handle.season != season && biome_handle.is_loaded() {
+        info!("{:?} has begun", season);
+        biome_handle.set_season(season);
+    }
+}
+
+/// Re-evaluate loaded chunks generated or last updated in a different season.
+fn queue_seasonal_updates(
+    chunk_handler: Res<ChunkHandler>,
+    biome_handle: Res<BiomeHandle>,
+    world_seed: Res<WorldSeed>,
+    mut seasonal_updates: ResMut<SeasonalUpdates>
+) {
+    let pool = AsyncComputeTaskPool::get();
+    let mut season_handles: Option<Arc<Vec<BiomeHandle>>> = None;
+
+    for (coordinate, chunk) in chunk_handler.chunks.iter() {
+        if chunk.season == Some(biome_handle.season) || seasonal_updates.tasks.contains_key(coordinate) {
+            continue;
+        }
+
+        let season_handles = season_handles
+            .get_or_insert_with(|| Arc::new(Season::ALL.iter()
+                .map(|season| biome_handle.for_season(*season))
+                .collect()))
+            .clone();
+        let season = biome_handle.season;
+        let world_seed = *world_seed;
+        let tiles = chunk.tiles;
+        let foliage_type = chunk.foliage_type;
+        let coordinate = *coordinate;
+        let chunk_season = chunk.season;
+
+        let task = pool.spawn(async move {
+            Chunk::seasonal_update(coordinate, tiles, foliage_type, chunk_season, season, &world_seed, &season_handles)
+        });
+
+        seasonal_updates.tasks.insert(coordinate, task);
+    }
+}
+
+/// Apply finished re-evaluations, skipping tiles changed since the chunk was evaluated.
+///
+/// Chunks that fail to re-evaluate keep their tiles and are not retried until the next season.
+fn apply_seasonal_updates(
+    biome_handle: Res<BiomeHandle>,
+    mut chunk_handler: ResMut<ChunkHandler>,
+    mut seasonal_updates: ResMut<SeasonalUpdates>,
+    mut tile_changed_events: EventWriter<TileChanged>
+) {
+    let mut finished = Vec::new();
+
+    for (coordinate, task) in seasonal_updates.tasks.iter_mut() {
+        if let Some(update) = future::block_on(future::poll_once(task)) {
+            finished.push((*coordinate, update));
+        }
+    }
+
+    for (coordinate, update) in finished {
+        seasonal_updates.tasks.remove(&coordinate);
+
+        let Some(chunk) = chunk_handler.get_chunk(coordinate) else {
+            continue;
+        };
+
+        let update = match update {
+            Ok(update) => update,
+            Err(error) => {
+                error!("Failed to update chunk {} for {:?}: {}", coordinate, biome_handle.season, error);
+                chunk.season = Some(biome_handle.season);
+                continue;
+            }
+        };
+
+        chunk.season = Some(update.season);
+
+        for change in update.changes {
+            if let Some(change) = chunk.apply_seasonal_change(change) {
+                tile_changed_events.send(change);
+            }
+        }
+
+        if update.tint_changed {
+            chunk_handler.chunks_to_remesh(coordinate);
+        }
+    }
+}
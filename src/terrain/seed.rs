@@ -0,0 +1,140 @@
+use std::env;
+use bevy::prelude::*;
+
+/// Environment variable read for the world seed when none is given on the command line.
+pub const SEED_ENV_VAR: &str = "CLI_MATE_SEED";
+
+/// Command line flag for the world seed, i.e. `--seed 1234` or `--seed random`.
+pub const SEED_ARG: &str = "--seed";
+
+// Salts used to derive the independent sub-seeds of each noise layer.
+const TERRAIN_SALT: u32 = 0x7E11_A1A5;
+const BIOME_SALT: u32 = 0xB10E_5EED;
+const FOLIAGE_SALT: u32 = 0xF011_A6E5;
+const TEMPERATURE_SALT: u32 = 0x7E39_9E7A;
+const MOISTURE_SALT: u32 = 0x3015_7E2E;
+const ELEVATION_SALT: u32 = 0xE1E7_A710;
+const BLEND_SALT: u32 = 0xB1E0_D5A1;
+const VARIANT_SALT: u32 = 0x5A21_A7E5;
+const WEATHER_SALT: u32 = 0x3EA7_E2A1;
+
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct WorldSeed {
+    pub seed: u32
+}
+
+impl WorldSeed {
+    pub fn new(
+        seed: u32
+    ) -> Self {
+        WorldSeed {
+            seed
+        }
+    }
+
+    pub fn random() -> Self {
+        WorldSeed::new(rand::random())
+    }
+
+    /// Read the seed from the `--seed` argument, falling back to the `CLI_MATE_SEED`
+    /// environment variable and then to seed 0.
+    ///
+    /// Either source accepts a number or `random`.
+    pub fn from_env() -> Self {
+        let arg = env::args()
+            .skip_while(|arg| arg != SEED_ARG)
+            .nth(1);
+
+        let value = arg.or_else(|| env::var(SEED_ENV_VAR).ok());
+
+        let world_seed = match value {
+            Some(value) => WorldSeed::parse(&value).unwrap_or_else(|| {
+                warn!("Invalid world seed '{}', using seed 0", value);
+                WorldSeed::default()
+            }),
+            None => WorldSeed::default()
+        };
+
+        info!("World seed: {}", world_seed.seed);
+
+        world_seed
+    }
+
+    pub fn parse(
+        value: &str
+    ) -> Option<Self> {
+        let value = value.trim();
+
+        if value.eq_ignore_ascii_case("random") {
+            return Some(WorldSeed::random());
+        }
+
+        value.parse().ok().map(WorldSeed::new)
+    }
+
+    /// Seed of the noise layer used to pick tiles within a biome.
+    pub fn terrain(&self) -> u32 {
+        self.derive(TERRAIN_SALT)
+    }
+
+    /// Seed of the noise layer used to pick biomes.
+    pub fn biome(&self) -> u32 {
+        self.derive(BIOME_SALT)
+    }
+
+    /// Seed of the noise layer used to place foliage.
+    pub fn foliage(&self) -> u32 {
+        self.derive(FOLIAGE_SALT)
+    }
+
+    /// Seed of the climate temperature noise layer.
+    pub fn temperature(&self) -> u32 {
+        self.derive(TEMPERATURE_SALT)
+    }
+
+    /// Seed of the climate moisture noise layer.
+    pub fn moisture(&self) -> u32 {
+        self.derive(MOISTURE_SALT)
+    }
+
+    /// Seed of the climate elevation noise layer.
+    pub fn elevation(&self) -> u32 {
+        self.derive(ELEVATION_SALT)
+    }
+
+    /// Seed of the white noise used to blend biome borders.
+    pub fn blend(&self) -> u32 {
+        self.derive(BLEND_SALT)
+    }
+
+    /// Seed of the white noise used to pick tile variants.
+    pub fn variant(&self) -> u32 {
+        self.derive(VARIANT_SALT)
+    }
+
+    /// Seed of the weather Markov chains.
+    pub fn weather(&self) -> u32 {
+        self.derive(WEATHER_SALT)
+    }
+
+    /// Mix the world seed with a salt using the splitmix64 finaliser, so
+    /// neighbouring world seeds do not produce neighbouring sub-seeds.
+    fn derive(
+        &self,
+        salt: u32
+    ) -> u32 {
+        let mut z = ((self.seed as u64) << 32 | salt as u64)
+            .wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        (z ^ (z >> 31)) as u32
+    }
+}
+
+impl Default for WorldSeed {
+    fn default() -> Self {
+        WorldSeed::new(0)
+    }
+}
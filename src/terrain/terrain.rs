@@ -1,114 +1,187 @@
+use bevy::asset::LoadState;
 use bevy::prelude::*;
-use crate::terrain::biome::{Biome, BiomeHandle, BiomeType, TileType};
-use crate::terrain::foliage::{Foliage, FoliageType};
-
-pub const TEXTURE_DIMENSION: f32 = 8.0;
-
-pub const DIRT:  usize = 0;
-pub const GRASS: usize = 1;
-pub const STONE: usize = 2;
-pub const SAND: usize = 3;
-pub const WATER: usize = TEXTURE_DIMENSION as usize;
+use crate::terrain::atlas::{TILE_ATLAS_PATH, TileAtlas, TileAtlasLoader};
+use crate::terrain::biome::BiomeHandle;
+use crate::terrain::chunk_handler::ChunkHandler;
+use crate::terrain::definition::{TERRAIN_DEFINITION_PATH, TerrainDefinition, TerrainDefinitionLoader};
+use crate::terrain::seed::WorldSeed;
 
 pub struct TerrainPlugin;
 
 impl Plugin for TerrainPlugin {
     fn build(&self, app: &mut App) {
+        // The `SavePlugin` owns the seed, fall back to the command line without it
+        if !app.world.contains_resource::<WorldSeed>() {
+            app.insert_resource(WorldSeed::from_env());
+        }
+
         app.insert_resource(
             BiomeHandle::new()
-        ).add_startup_system(init_biomes);
+        ).add_asset::<TerrainDefinition>()
+            .init_asset_loader::<TerrainDefinitionLoader>()
+            .add_startup_system(load_terrain_definition)
+            .add_system(apply_terrain_definition)
+            .add_system(report_terrain_definition_failure)
+            .init_resource::<TileAtlas>()
+            .add_asset::<TileAtlas>()
+            .init_asset_loader::<TileAtlasLoader>()
+            .add_startup_system(load_tile_atlas)
+            .add_system(apply_tile_atlas);
     }
 }
 
-fn init_biomes(
-    mut biome_handle: ResMut<BiomeHandle>
+#[derive(Resource)]
+pub struct TerrainDefinitionHandle(pub Handle<TerrainDefinition>);
+
+#[derive(Resource)]
+pub struct TileAtlasHandle(pub Handle<TileAtlas>);
+
+fn load_terrain_definition(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>
 ) {
+    commands.insert_resource(TerrainDefinitionHandle(asset_server.load(TERRAIN_DEFINITION_PATH)));
+}
 
-    // Tile Type Init
-    let grass_tile: TileType = TileType {
-        tile_type: GRASS,
-        weight: 10
-    };
+fn load_tile_atlas(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>
+) {
+    commands.insert_resource(TileAtlasHandle(asset_server.load(TILE_ATLAS_PATH)));
+}
 
-    let dirt_tile: TileType = TileType {
-        tile_type: DIRT,
-        weight: 5
+/// Replace the tile atlas resource whenever the atlas is loaded or hot reloaded, remeshing
+/// every loaded chunk with the new uvs.
+fn apply_tile_atlas(
+    mut atlas_events: EventReader<AssetEvent<TileAtlas>>,
+    atlases: Res<Assets<TileAtlas>>,
+    atlas_handle: Option<Res<TileAtlasHandle>>,
+    mut tile_atlas: ResMut<TileAtlas>,
+    biome_handle: Res<BiomeHandle>,
+    mut chunk_handler: ResMut<ChunkHandler>
+) {
+    let Some(atlas_handle) = atlas_handle else {
+        return;
     };
 
-    let stone_tile: TileType = TileType {
-        tile_type: STONE,
-        weight: 5
-    };
+    for event in atlas_events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else {
+            continue;
+        };
 
-    let sand_tile: TileType = TileType {
-        tile_type: SAND,
-        weight: 5
-    };
+        if *handle != atlas_handle.0 {
+            continue;
+        }
 
-    let water_tile: TileType = TileType {
-        tile_type: WATER,
-        weight: 6
-    };
+        let Some(atlas) = atlases.get(handle) else {
+            continue;
+        };
 
-    // Foliage Init
-    let cactus: Foliage = Foliage {
-        foliage_type: FoliageType::CACTUS,
-        weight: 10,
-        scale: 1.0 / 16.0,
-        spawns_on: vec![SAND]
-    };
+        if let Err(error) = atlas.check_tiles(&biome_handle.tiles) {
+            error!("Invalid tile atlas '{}': {}. Keeping the previous atlas", TILE_ATLAS_PATH, error);
+            continue;
+        }
+
+        if *tile_atlas != *atlas {
+            *tile_atlas = atlas.clone();
 
-    let rose: Foliage = Foliage {
-        foliage_type: FoliageType::ROSE,
-        weight: 10,
-        scale: 1.0 / 16.0,
-        spawns_on: vec![GRASS]
+            let coordinates: Vec<IVec2> = chunk_handler.chunks.keys().copied().collect();
+            for coordinate in coordinates {
+                chunk_handler.chunks_to_remesh(coordinate);
+            }
+        }
+
+        info!("Loaded tile atlas '{}'", TILE_ATLAS_PATH);
+    }
+}
+
+/// Rebuild the biome handle whenever the terrain definition is loaded or hot reloaded.
+///
+/// On reload every loaded chunk is unloaded so it is regenerated from the new definition.
+fn apply_terrain_definition(
+    mut commands: Commands,
+    mut definition_events: EventReader<AssetEvent<TerrainDefinition>>,
+    definitions: Res<Assets<TerrainDefinition>>,
+    definition_handle: Option<Res<TerrainDefinitionHandle>>,
+    mut biome_handle: ResMut<BiomeHandle>,
+    tile_atlas: Res<TileAtlas>,
+    mut chunk_handler: ResMut<ChunkHandler>
+) {
+    let Some(definition_handle) = definition_handle else {
+        return;
     };
 
-    let rock: Foliage = Foliage {
-        foliage_type: FoliageType::ROCK,
-        weight: 10,
-        scale: 1.0 / 16.0,
-        spawns_on: vec![STONE, DIRT, GRASS]
+    for event in definition_events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else {
+            continue;
+        };
+
+        if *handle != definition_handle.0 {
+            continue;
+        }
+
+        let Some(definition) = definitions.get(handle) else {
+            continue;
+        };
+
+        let new_biome_handle = tile_atlas.check_tiles(&definition.tiles)
+            .and_then(|_| BiomeHandle::from_definition(definition));
+
+        let new_biome_handle = match new_biome_handle {
+            Ok(new_biome_handle) => new_biome_handle,
+            Err(error) => {
+                error!("Invalid terrain definition '{}': {}. {}",
+                       TERRAIN_DEFINITION_PATH, error, definition_failure_consequence(&biome_handle));
+                continue;
+            }
+        };
+
+        // Unload with the old biome handle, modified chunks keep the ids of its definitions.
+        let coordinates: Vec<IVec2> = chunk_handler.chunks.keys()
+            .chain(chunk_handler.pending_chunks.keys())
+            .copied()
+            .collect();
+
+        for coordinate in coordinates {
+            if let Some(entity) = chunk_handler.remove_chunk(coordinate, &biome_handle) {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+
+        chunk_handler.failed_chunks.clear();
+        *biome_handle = new_biome_handle;
+        info!("Loaded terrain definition '{}'", TERRAIN_DEFINITION_PATH);
+    }
+}
+
+/// Explain what happens to the world when the terrain definition fails to parse or
+/// validate, the asset server only logs the error itself.
+fn report_terrain_definition_failure(
+    asset_server: Res<AssetServer>,
+    definition_handle: Option<Res<TerrainDefinitionHandle>>,
+    biome_handle: Res<BiomeHandle>,
+    mut reported: Local<bool>
+) {
+    let Some(definition_handle) = definition_handle else {
+        return;
     };
 
-    // Biome 0: PLAINS BIOME
-    let plains_biome: Biome = Biome::new(20, BiomeType::PLAINS, 0.3)
-        .add_tile(grass_tile.clone())
-        .add_tile(dirt_tile.clone())
-        .add_tile(stone_tile.clone())
-        .add_tile(water_tile.clone())
-        .add_foliage(rock.clone());
-
-    // Biome 1: DESERT BIOME
-    let desert_biome: Biome = Biome::new(10, BiomeType::DESERT, 0.3)
-        .add_tile(dirt_tile.clone().set_weight(4))
-        .add_tile(sand_tile.clone().set_weight(20))
-        .add_foliage(cactus.clone());
-
-    // Biome 2: GRASSLANDS BIOME
-    let grassland_biome: Biome = Biome::new(10, BiomeType::GRASSLAND, 0.3)
-        .add_tile(grass_tile.clone().set_weight(20))
-        .add_tile(dirt_tile.clone().set_weight(4))
-        .add_tile(stone_tile.clone().set_weight(4))
-        .add_tile(water_tile.clone().set_weight(10))
-        .add_foliage(rose.clone())
-        .add_foliage(rock.clone());
-
-    // Biome 3: BEACH BIOME
-    let beach_biome: Biome = Biome::new(5, BiomeType::BEACH, 0.0)
-        .add_tile(water_tile.clone().set_weight(4))
-        .add_tile(sand_tile.clone().set_weight(10));
-
-    let ocean_biome: Biome = Biome::new(30, BiomeType::OCEAN, 0.0)
-        .add_tile(water_tile.clone().set_weight(30))
-        .add_tile(sand_tile.clone().set_weight(4));
-
-    // Add Biomes To Biome Handle
-    biome_handle.add_biome(plains_biome);
-    biome_handle.add_biome(desert_biome);
-    biome_handle.add_biome(grassland_biome);
-    biome_handle.add_biome(beach_biome);
-    biome_handle.add_biome(ocean_biome);
+    let failed = asset_server.get_load_state(&definition_handle.0) == LoadState::Failed;
+
+    if failed && !*reported {
+        error!("Failed to load terrain definition '{}', see the error above. {}",
+               TERRAIN_DEFINITION_PATH, definition_failure_consequence(&biome_handle));
+    }
+
+    *reported = failed;
 }
 
+fn definition_failure_consequence(
+    biome_handle: &BiomeHandle
+) -> &'static str {
+    if biome_handle.is_loaded() {
+        "Keeping the previous definition"
+    } else {
+        "No terrain will be generated until it is fixed, the file is reloaded when saved"
+    }
+}
@@ -1,16 +1,34 @@
+use bevy::prelude::IVec2;
+use serde::{Deserialize, Serialize};
 use crate::terrain::biome::BiomeType;
+use crate::terrain::chunk::chunk_to_tile_coordinate;
+use crate::terrain::foliage::FoliageType;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
 pub struct Tile {
     pub tile: usize,
     pub biome: BiomeType
 }
 
-impl Tile {
-    pub fn default() -> Tile {
-        Tile {
-            tile: 0,
-            biome: BiomeType::PLAINS
-        }
+/// Sent whenever a loaded tile or its foliage is changed after generation.
+#[derive(Clone, Copy)]
+pub struct TileChanged {
+    pub chunk_coordinate: IVec2,
+    pub x: usize,
+    pub y: usize,
+    pub old_tile: Tile,
+    pub new_tile: Tile,
+    pub old_foliage: FoliageType,
+    pub new_foliage: FoliageType
+}
+
+impl TileChanged {
+    pub fn foliage_changed(&self) -> bool {
+        self.old_foliage != self.new_foliage
     }
-}
\ No newline at end of file
+
+    /// World tile coordinate of the changed tile.
+    pub fn tile_coordinate(&self) -> IVec2 {
+        chunk_to_tile_coordinate(self.chunk_coordinate, self.x, self.y)
+    }
+}
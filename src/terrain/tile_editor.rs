@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use crate::MainCamera;
+use crate::terrain::biome::BiomeHandle;
+use crate::terrain::chunk::{TILE_SIZE, tile_to_chunk_coordinate, world_to_tile_coordinate};
+use crate::terrain::chunk_handler::ChunkHandler;
+use crate::terrain::foliage::FoliageType;
+use crate::terrain::tile::TileChanged;
+
+/// Number keys selecting the tile to place, in the order tiles are defined in the
+/// terrain definition.
+const EDITOR_TILE_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9
+];
+
+/// Id of the tile left behind when a tile is broken.
+pub const BROKEN_TILE: &str = "dirt";
+
+pub struct TileEditorPlugin;
+impl Plugin for TileEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileEditor>()
+            .add_startup_system(spawn_tile_highlight)
+            .add_system(select_tile)
+            .add_system(update_hovered_tile)
+            .add_system(edit_tiles.after(update_hovered_tile));
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct TileEditor {
+    pub selected_tile: usize,
+    /// World position of the bottom left corner of the tile under the cursor.
+    pub hovered_tile: Option<Vec2>
+}
+
+#[derive(Component)]
+struct TileHighlight;
+
+fn spawn_tile_highlight(
+    mut commands: Commands
+) {
+    commands.spawn((SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgba(1.0, 1.0, 1.0, 0.3),
+            custom_size: Some(Vec2::splat(TILE_SIZE)),
+            ..default()
+        },
+        visibility: Visibility { is_visible: false },
+        ..default()
+    }, TileHighlight));
+}
+
+fn select_tile(
+    keyboard_input: Res<Input<KeyCode>>,
+    biome_handle: Res<BiomeHandle>,
+    mut tile_editor: ResMut<TileEditor>
+) {
+    for (tile, key) in EDITOR_TILE_KEYS.iter().enumerate().take(biome_handle.tiles.len()) {
+        if keyboard_input.just_pressed(*key) {
+            tile_editor.selected_tile = tile;
+        }
+    }
+}
+
+fn update_hovered_tile(
+    windows: Res<Windows>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut tile_editor: ResMut<TileEditor>,
+    mut highlights: Query<(&mut Transform, &mut Visibility), With<TileHighlight>>
+) {
+    let cursor_position = windows.get_primary()
+        .and_then(|window| window.cursor_position());
+
+    let hovered_tile = cursor_position.and_then(|cursor_position| {
+        let (camera, camera_transform) = cameras.get_single().ok()?;
+        let ray = camera.viewport_to_world(camera_transform, cursor_position)?;
+
+        Some((ray.origin.truncate() / TILE_SIZE).floor() * TILE_SIZE)
+    });
+
+    tile_editor.hovered_tile = hovered_tile;
+
+    for (mut transform, mut visibility) in highlights.iter_mut() {
+        visibility.is_visible = hovered_tile.is_some();
+
+        if let Some(tile_position) = hovered_tile {
+            transform.translation = (tile_position + TILE_SIZE / 2.0).extend(1.5);
+        }
+    }
+}
+
+/// Each left click breaks the hovered tile's foliage, or the tile itself when it has none, and
+/// holding right click paints the selected tile.
+fn edit_tiles(
+    mouse_input: Res<Input<MouseButton>>,
+    tile_editor: Res<TileEditor>,
+    biome_handle: Res<BiomeHandle>,
+    mut chunk_handler: ResMut<ChunkHandler>,
+    mut tile_changed_events: EventWriter<TileChanged>
+) {
+    let breaking = mouse_input.just_pressed(MouseButton::Left);
+    if !breaking && !mouse_input.pressed(MouseButton::Right) {
+        return;
+    }
+
+    let Some(tile_position) = tile_editor.hovered_tile else {
+        return;
+    };
+
+    // Sample the tile centre so the position never lands on a chunk border.
+    let tile_coordinate = world_to_tile_coordinate(tile_position + TILE_SIZE / 2.0);
+    let Some(foliage) = chunk_handler.get_foliage(tile_coordinate) else {
+        return;
+    };
+
+    let has_foliage = foliage != FoliageType::NONE;
+    let (chunk_coordinate, x, y) = tile_to_chunk_coordinate(tile_coordinate);
+
+    let change = if breaking && has_foliage {
+        chunk_handler.update_foliage(chunk_coordinate, x, y, FoliageType::NONE, &biome_handle)
+    } else if breaking {
+        let Some(broken_tile) = biome_handle.tile_id(BROKEN_TILE) else {
+            return;
+        };
+
+        chunk_handler.update_chunk(chunk_coordinate, x, y, broken_tile, &biome_handle)
+    } else {
+        chunk_handler.update_chunk(chunk_coordinate, x, y, tile_editor.selected_tile, &biome_handle)
+    };
+
+    if let Ok(Some(change)) = change {
+        tile_changed_events.send(change);
+    }
+}
@@ -0,0 +1,421 @@
+use std::f32::consts::TAU;
+use bevy::prelude::*;
+use serde::Deserialize;
+use crate::MainCamera;
+use crate::clock::{Daylight, WorldClock, advance_clock};
+use crate::entities::player::Player;
+use crate::terrain::biome::BiomeHandle;
+use crate::terrain::chunk::world_to_tile_coordinate;
+use crate::terrain::chunk_handler::{ChunkHandler, ChunkMaterialHandle};
+use crate::terrain::chunk_material::{ChunkMaterial, MAX_WEATHER_BIOMES};
+use crate::terrain::definition::WeatherTransitionDefinition;
+use crate::terrain::noise::position_hash;
+use crate::terrain::season::Season;
+use crate::terrain::seed::WorldSeed;
+
+/// Times a day the weather of every biome may change.
+pub const WEATHER_STEPS_PER_DAY: u32 = 24;
+
+/// Seconds for weather to fade in or out.
+pub const WEATHER_FADE_SECONDS: f32 = 5.0;
+
+/// Seconds of full rain to soak the ground and of clear weather to dry it again.
+pub const WETTING_SECONDS: f32 = 60.0;
+pub const DRYING_SECONDS: f32 = 180.0;
+
+/// Seconds of full snow to cover the ground and of other weather to melt it again.
+pub const SNOWING_SECONDS: f32 = 90.0;
+pub const MELTING_SECONDS: f32 = 240.0;
+
+/// Size of the area around the camera precipitation is spawned in, in world units.
+pub const PRECIPITATION_AREA: Vec2 = Vec2::new(48.0, 28.0);
+pub const MAX_PRECIPITATION: usize = 800;
+
+/// Smallest change in wetness or snow cover of a biome applied to the chunk material.
+const GROUND_STEP: f32 = 1.0 / 255.0;
+
+pub struct WeatherPlugin;
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BiomeWeather>()
+            .init_resource::<CurrentWeather>()
+            .add_system(update_biome_weather.after(advance_clock))
+            .add_system(update_current_weather.after(update_biome_weather))
+            .add_system(apply_weather_to_chunks.after(update_current_weather))
+            .add_system(spawn_precipitation.after(update_current_weather))
+            .add_system(update_precipitation);
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Weather {
+    #[default]
+    Clear,
+    /// Turns to snow in winter.
+    Rain,
+    Snow,
+    Sandstorm
+}
+
+impl Weather {
+    /// Fraction of the player's acceleration left at full intensity.
+    pub fn movement_factor(self) -> f32 {
+        match self {
+            Weather::Clear => 1.0,
+            Weather::Rain => 0.85,
+            Weather::Snow => 0.7,
+            Weather::Sandstorm => 0.6
+        }
+    }
+
+    /// Speed the wind pushes the player with at full intensity, in world units per second.
+    pub fn wind_speed(self) -> f32 {
+        match self {
+            Weather::Clear => 0.0,
+            Weather::Rain => 0.5,
+            Weather::Snow => 0.3,
+            Weather::Sandstorm => 2.5
+        }
+    }
+
+    /// Weather actually falling in `season`, rain turns to snow in winter.
+    pub fn in_season(
+        self,
+        season: Season
+    ) -> Self {
+        match (self, season) {
+            (Weather::Rain, Season::Winter) => Weather::Snow,
+            (weather, _) => weather
+        }
+    }
+
+    fn particles_per_second(self) -> f32 {
+        match self {
+            Weather::Clear => 0.0,
+            Weather::Rain => 300.0,
+            Weather::Snow => 120.0,
+            Weather::Sandstorm => 400.0
+        }
+    }
+}
+
+/// Markov chain picking the weather of a biome from the weather before it.
+#[derive(Clone, Debug, Default)]
+pub struct WeatherChain {
+    transitions: Vec<(Weather, Vec<(Weather, u16)>)>
+}
+
+impl WeatherChain {
+    pub fn from_definition(
+        transitions: &[WeatherTransitionDefinition]
+    ) -> Self {
+        WeatherChain {
+            transitions: transitions.iter()
+                .map(|transition| (
+                    transition.from,
+                    transition.to.iter().map(|to| (to.weather, to.weight)).collect()
+                ))
+                .collect()
+        }
+    }
+
+    /// Weather following `current` for a uniformly distributed `rng` in [0, 1), clear when
+    /// the chain has no transitions from `current`.
+    pub fn next(
+        &self,
+        current: Weather,
+        rng: f32
+    ) -> Weather {
+        let Some((_, to)) = self.transitions.iter().find(|(from, _)| *from == current) else {
+            return Weather::Clear;
+        };
+
+        let weight_sum: u32 = to.iter().map(|(_, weight)| *weight as u32).sum();
+        let val = (rng * weight_sum as f32) as u32;
+        let mut sum = 0;
+
+        for (weather, weight) in to {
+            sum += *weight as u32;
+            if sum > val {
+                return *weather;
+            }
+        }
+
+        Weather::Clear
+    }
+}
+
+/// Weather of every biome, indexed by `BiomeType`, at a weather step of the world clock,
+/// along with its lasting effects on the ground of each biome.
+///
+/// Chains always start clear at step 0 and are advanced with a hash of the world seed, the
+/// step and the biome, so the weather at any time is the same for a given seed.
+#[derive(Resource, Default)]
+pub struct BiomeWeather {
+    pub step: u32,
+    pub weather: Vec<Weather>,
+    /// How soaked the ground is by rain, from 0 to 1.
+    pub wetness: Vec<f32>,
+    /// How much of the ground is covered in snow, from 0 to 1.
+    pub snow_cover: Vec<f32>
+}
+
+impl BiomeWeather {
+    /// Replay the chains from step 0 on the next `advance_to`, the ground of each biome is
+    /// kept as it dries or melts on its own.
+    pub fn restart(&mut self) {
+        self.step = 0;
+        self.weather.clear();
+    }
+
+    pub fn advance_to(
+        &mut self,
+        step: u32,
+        biome_handle: &BiomeHandle,
+        seed: &WorldSeed
+    ) {
+        self.weather.resize(biome_handle.biomes.len(), Weather::Clear);
+        self.wetness.resize(biome_handle.biomes.len(), 0.0);
+        self.snow_cover.resize(biome_handle.biomes.len(), 0.0);
+
+        while self.step < step {
+            self.step += 1;
+
+            for (index, biome) in biome_handle.biomes.iter().enumerate() {
+                let rng = position_hash(seed.weather(), IVec2::new(self.step as i32, index as i32));
+                self.weather[index] = biome.weather.next(self.weather[index], rng);
+            }
+        }
+    }
+
+    /// Wet or snow over the ground of biomes where it rains or snows for `dt` seconds, and
+    /// dry or melt it everywhere else.
+    pub fn update_ground(
+        &mut self,
+        dt: f32,
+        season: Season
+    ) {
+        for (index, weather) in self.weather.iter().enumerate() {
+            let weather = weather.in_season(season);
+
+            self.wetness[index] = match weather {
+                Weather::Rain => self.wetness[index] + dt / WETTING_SECONDS,
+                _ => self.wetness[index] - dt / DRYING_SECONDS
+            }.clamp(0.0, 1.0);
+
+            self.snow_cover[index] = match weather {
+                Weather::Snow => self.snow_cover[index] + dt / SNOWING_SECONDS,
+                _ => self.snow_cover[index] - dt / MELTING_SECONDS
+            }.clamp(0.0, 1.0);
+        }
+    }
+}
+
+/// Weather at the player.
+#[derive(Resource, Clone, Copy, PartialEq, Debug, Default)]
+pub struct CurrentWeather {
+    pub weather: Weather,
+    /// Fades from 0 to 1 as the weather sets in, and back to 0 before it changes.
+    pub intensity: f32,
+    pub wind: Vec2
+}
+
+impl CurrentWeather {
+    /// Fraction of the player's acceleration left by the weather.
+    pub fn movement_factor(&self) -> f32 {
+        1.0 + (self.weather.movement_factor() - 1.0) * self.intensity
+    }
+}
+
+fn weather_step(
+    world_clock: &WorldClock
+) -> u32 {
+    world_clock.day * WEATHER_STEPS_PER_DAY + (world_clock.time_of_day * WEATHER_STEPS_PER_DAY as f32) as u32
+}
+
+/// Advance the weather chains of every biome to the current weather step and update the
+/// ground of each biome.
+fn update_biome_weather(
+    time: Res<Time>,
+    world_clock: Res<WorldClock>,
+    biome_handle: Res<BiomeHandle>,
+    world_seed: Res<WorldSeed>,
+    mut biome_weather: ResMut<BiomeWeather>,
+    mut last_season: Local<Option<Season>>
+) {
+    // Replay the chains from the start when the definition or seed changes, they only depend
+    // on the seed. Switching seasons changes the biome handle too but keeps the chains.
+    let season_changed = last_season.replace(biome_handle.season)
+        .is_some_and(|season| season != biome_handle.season);
+
+    if (biome_handle.is_changed() && !season_changed) || world_seed.is_changed() {
+        biome_weather.restart();
+    }
+
+    biome_weather.advance_to(weather_step(&world_clock), &biome_handle, &world_seed);
+    biome_weather.update_ground(time.delta_seconds(), Season::from_day(world_clock.day));
+}
+
+/// Fade to the weather of the biome the player stands in.
+fn update_current_weather(
+    time: Res<Time>,
+    world_clock: Res<WorldClock>,
+    world_seed: Res<WorldSeed>,
+    biome_weather: Res<BiomeWeather>,
+    chunk_handler: Res<ChunkHandler>,
+    players: Query<&Player>,
+    mut current_weather: ResMut<CurrentWeather>
+) {
+    let dt = time.delta_seconds();
+    let biome_weather_at = |player: &Player| {
+        let tile = chunk_handler.get_tile(world_to_tile_coordinate(player.pos))?;
+        biome_weather.weather.get(tile.biome.0 as usize).copied()
+    };
+
+    let Some(target) = players.iter().next().and_then(biome_weather_at) else {
+        return;
+    };
+    let target = target.in_season(Season::from_day(world_clock.day));
+
+    let current_weather = current_weather.as_mut();
+
+    if current_weather.weather != target {
+        current_weather.intensity -= dt / WEATHER_FADE_SECONDS;
+
+        if current_weather.intensity <= 0.0 {
+            info!("Weather changed to {:?}", target);
+            current_weather.weather = target;
+            current_weather.intensity = 0.0;
+        }
+    } else {
+        current_weather.intensity = (current_weather.intensity + dt / WEATHER_FADE_SECONDS).min(1.0);
+    }
+
+    let wind_angle = position_hash(world_seed.weather(), IVec2::new(weather_step(&world_clock) as i32, -1)) * TAU;
+    current_weather.wind = Vec2::from_angle(wind_angle)
+        * current_weather.weather.wind_speed()
+        * current_weather.intensity;
+}
+
+/// Show puddles and snow cover on the tiles of each biome through the shared chunk
+/// material.
+fn apply_weather_to_chunks(
+    biome_weather: Res<BiomeWeather>,
+    chunk_material: Option<Res<ChunkMaterialHandle>>,
+    mut materials: ResMut<Assets<ChunkMaterial>>
+) {
+    let Some(chunk_material) = chunk_material else {
+        return;
+    };
+
+    let mut weather = [Vec4::ZERO; MAX_WEATHER_BIOMES];
+    let ground = biome_weather.wetness.iter().zip(&biome_weather.snow_cover);
+
+    for (biome_ground, (wetness, snow_cover)) in weather.iter_mut().zip(ground) {
+        *biome_ground = Vec4::new(*wetness, *snow_cover, 0.0, 0.0);
+    }
+
+    let changed = materials.get(&chunk_material.0).is_some_and(|material| material.weather.iter()
+        .zip(&weather)
+        .any(|(old, new)| (*old - *new).abs().max_element() >= GROUND_STEP));
+
+    if changed {
+        if let Some(material) = materials.get_mut(&chunk_material.0) {
+            material.weather = weather;
+        }
+    }
+}
+
+/// Raindrop, snowflake or grain of sand falling around the camera.
+#[derive(Component)]
+pub struct Precipitation {
+    pub velocity: Vec2,
+    pub lifetime: f32
+}
+
+fn spawn_precipitation(
+    mut commands: Commands,
+    time: Res<Time>,
+    current_weather: Res<CurrentWeather>,
+    daylight: Res<Daylight>,
+    cameras: Query<&Transform, With<MainCamera>>,
+    particles: Query<(), With<Precipitation>>,
+    mut spawn_accumulator: Local<f32>
+) {
+    let Ok(camera) = cameras.get_single() else {
+        return;
+    };
+
+    let weather = current_weather.weather;
+    *spawn_accumulator += weather.particles_per_second() * current_weather.intensity * time.delta_seconds();
+
+    let room = MAX_PRECIPITATION.saturating_sub(particles.iter().count());
+    let count = (*spawn_accumulator as usize).min(room);
+    *spawn_accumulator = spawn_accumulator.fract();
+
+    for _ in 0..count {
+        let offset = (Vec2::new(rand::random(), rand::random()) - 0.5) * PRECIPITATION_AREA;
+        let position = camera.translation.truncate() + offset;
+        let jitter = rand::random::<f32>();
+
+        let (size, color, velocity, lifetime) = match weather {
+            Weather::Rain => (
+                Vec2::new(0.04, 0.5),
+                Color::rgba(0.7, 0.8, 1.0, 0.6),
+                Vec2::new(current_weather.wind.x * 4.0, -20.0 - 5.0 * jitter),
+                0.4 + 0.4 * jitter
+            ),
+            Weather::Snow => (
+                Vec2::splat(0.1 + 0.06 * jitter),
+                Color::rgba(1.0, 1.0, 1.0, 0.9),
+                Vec2::new(current_weather.wind.x * 2.0 + jitter - 0.5, -2.0 - jitter),
+                3.0 + 2.0 * jitter
+            ),
+            Weather::Sandstorm => (
+                Vec2::splat(0.06 + 0.04 * jitter),
+                Color::rgba(0.85, 0.7, 0.45, 0.7),
+                current_weather.wind * (5.0 + 3.0 * jitter),
+                1.5 + jitter
+            ),
+            Weather::Clear => continue
+        };
+
+        let color = Color::rgba(
+            color.r() * daylight.0.r(),
+            color.g() * daylight.0.g(),
+            color.b() * daylight.0.b(),
+            color.a());
+
+        commands.spawn((SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(size),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(3.0))
+                .with_rotation(Quat::from_rotation_z(velocity.y.atan2(velocity.x) + TAU / 4.0)),
+            ..default()
+        }, Precipitation {
+            velocity,
+            lifetime
+        }));
+    }
+}
+
+fn update_precipitation(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut particles: Query<(Entity, &mut Transform, &mut Precipitation)>
+) {
+    let dt = time.delta_seconds();
+
+    for (entity, mut transform, mut particle) in particles.iter_mut() {
+        particle.lifetime -= dt;
+
+        if particle.lifetime <= 0.0 {
+            commands.entity(entity).despawn();
+        } else {
+            transform.translation += (particle.velocity * dt).extend(0.0);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+use bevy::render::mesh::VertexAttributeValues;
+use cli_mate::TerrainDefinition;
+use cli_mate::terrain::atlas::TileAtlas;
+use cli_mate::terrain::chunk_material::ATTRIBUTE_ATLAS_RECT;
+use cli_mate::terrain::definition::TerrainDefinitionError;
+use cli_mate::terrain::meshing::{ChunkTileMapBuilder, TileAppearance};
+
+/// Uvs of a single tile quad, bottom left, top left, bottom right and top right.
+const TILE_UVS: [[f32; 2]; 4] = [[0.0, 1.0], [0.0, 0.0], [1.0, 1.0], [1.0, 0.0]];
+
+/// Uvs and atlas rectangles of the vertices of a mesh holding one tile of `cell`.
+fn tile_mesh_attributes(
+    atlas: &TileAtlas,
+    cell: usize
+) -> (Vec<[f32; 2]>, Vec<[f32; 4]>) {
+    let mut builder = ChunkTileMapBuilder::new();
+    builder.add_tile(Vec2::ZERO, &TileAppearance { cell, ..default() }, atlas);
+    let mesh = builder.build();
+
+    let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) else {
+        panic!("mesh has no uvs");
+    };
+    let Some(VertexAttributeValues::Float32x4(atlas_rects)) = mesh.attribute(ATTRIBUTE_ATLAS_RECT) else {
+        panic!("mesh has no atlas rectangles");
+    };
+
+    (uvs.clone(), atlas_rects.clone())
+}
+
+#[test]
+fn first_cell_atlas_rect() {
+    let (uvs, atlas_rects) = tile_mesh_attributes(&TileAtlas::default(), 0);
+
+    assert_eq!(uvs, TILE_UVS);
+    assert_eq!(atlas_rects, vec![[0.5 / 128.0, 0.5 / 256.0, 15.0 / 128.0, 15.0 / 256.0]; 4]);
+}
+
+#[test]
+fn cells_are_numbered_by_rows_from_the_top_left() {
+    let atlas = TileAtlas::default();
+
+    let (uvs, atlas_rects) = tile_mesh_attributes(&atlas, 9);
+    assert_eq!(uvs, TILE_UVS);
+    assert_eq!(atlas_rects, vec![[16.5 / 128.0, 16.5 / 256.0, 15.0 / 128.0, 15.0 / 256.0]; 4]);
+
+    let (uvs, atlas_rects) = tile_mesh_attributes(&atlas, 63);
+    assert_eq!(uvs, TILE_UVS);
+    assert_eq!(atlas_rects, vec![[112.5 / 128.0, 112.5 / 256.0, 15.0 / 128.0, 15.0 / 256.0]; 4]);
+}
+
+#[test]
+fn padding_is_skipped_between_cells() {
+    let atlas = TileAtlas {
+        texture: "tiles/padded.png".to_string(),
+        cell_size: 16,
+        columns: 2,
+        rows: 2,
+        padding: 2
+    };
+
+    assert_eq!(atlas.texture_size().to_array(), [34.0, 34.0]);
+
+    let (uvs, atlas_rects) = tile_mesh_attributes(&atlas, 3);
+    let size = 33.5 / 34.0 - 18.5 / 34.0;
+    assert_eq!(uvs, TILE_UVS);
+    assert_eq!(atlas_rects, vec![[18.5 / 34.0, 18.5 / 34.0, size, size]; 4]);
+}
+
+#[test]
+fn cell_rect_is_inset_by_half_a_texel() {
+    let atlas = TileAtlas::default();
+    let rect = atlas.cell_rect(1);
+
+    assert_eq!(rect.min.to_array(), [16.5 / 128.0, 0.5 / 256.0]);
+    assert_eq!(rect.max.to_array(), [31.5 / 128.0, 15.5 / 256.0]);
+}
+
+#[test]
+fn tile_cells_must_be_in_the_atlas() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/terrain/default.terrain.ron");
+    let mut definition = TerrainDefinition::from_bytes(&std::fs::read(path).unwrap()).unwrap();
+    let atlas = TileAtlas::default();
+
+    assert_eq!(atlas.check_tiles(&definition.tiles), Ok(()));
+
+    let grass = definition.tiles.iter_mut().find(|tile| tile.id == "grass").unwrap();
+    grass.variants.as_mut().unwrap().cells.push(128);
+
+    assert_eq!(atlas.check_tiles(&definition.tiles), Err(TerrainDefinitionError::InvalidAtlasCell {
+        tile: "grass".to_string(),
+        cell: 128,
+        cell_count: 128
+    }));
+}
+
+#[test]
+fn autotile_sets_and_animation_frames_must_be_in_the_atlas() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/terrain/default.terrain.ron");
+    let definition = TerrainDefinition::from_bytes(&std::fs::read(path).unwrap()).unwrap();
+    let atlas = TileAtlas::default();
+    let invalid_water_cell = |cell: usize| Err(TerrainDefinitionError::InvalidAtlasCell {
+        tile: "water".to_string(),
+        cell,
+        cell_count: 128
+    });
+
+    // The last blob cell of the last frame, 16 + 46 + 65, is the last cell of the atlas
+    let mut tiles = definition.tiles;
+    let water = tiles.iter().position(|tile| tile.id == "water").unwrap();
+    tiles[water].animation.as_mut().unwrap().frame_offset = 65;
+    assert_eq!(atlas.check_tiles(&tiles), Ok(()));
+
+    tiles[water].animation.as_mut().unwrap().frame_offset = 66;
+    assert_eq!(atlas.check_tiles(&tiles), invalid_water_cell(128));
+
+    // Without the animation only the blob set has to fit
+    tiles[water].animation = None;
+    tiles[water].autotile.as_mut().unwrap().atlas_start = 82;
+    assert_eq!(atlas.check_tiles(&tiles), invalid_water_cell(128));
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use bevy::prelude::IVec2;
+use cli_mate::ChunkData;
+use cli_mate::terrain::autotile::{Autotile, TileNeighbourhood};
+use cli_mate::terrain::chunk::{Chunk, CHUNK_SIZE, NEIGHBOUR_OFFSETS};
+use cli_mate::terrain::definition::AutotileMode;
+use cli_mate::terrain::foliage::FoliageType;
+use cli_mate::terrain::season::Season;
+use cli_mate::terrain::tile::Tile;
+
+const NORTH: u8 = 1;
+const NORTH_EAST: u8 = 2;
+const EAST: u8 = 4;
+const SOUTH_EAST: u8 = 8;
+const SOUTH: u8 = 16;
+const SOUTH_WEST: u8 = 32;
+const WEST: u8 = 64;
+const NORTH_WEST: u8 = 128;
+
+const ATLAS_START: usize = 16;
+const WATER: usize = 1;
+
+/// Blob cell of a water tile whose neighbours are water where the bit of `mask` is set,
+/// bits follow `NEIGHBOUR_OFFSETS`.
+fn blob_cell(
+    mask: u8
+) -> usize {
+    let mut tiles = [[Tile::default(); CHUNK_SIZE]; CHUNK_SIZE];
+    tiles[1][1].tile = WATER;
+
+    for (bit, offset) in NEIGHBOUR_OFFSETS.iter().enumerate() {
+        if mask & 1 << bit != 0 {
+            tiles[(1 + offset.x) as usize][(1 + offset.y) as usize].tile = WATER;
+        }
+    }
+
+    let chunk = Chunk::from_data(ChunkData {
+        coordinate: IVec2::ZERO,
+        tiles,
+        foliage_type: [[FoliageType::NONE; CHUNK_SIZE]; CHUNK_SIZE],
+        season: Season::default()
+    });
+    let neighbourhood = TileNeighbourhood::new(&chunk, |_| Some(0));
+
+    let autotile = Autotile {
+        mode: AutotileMode::Blob,
+        atlas_start: ATLAS_START,
+        connects_to: Vec::new()
+    };
+
+    autotile.atlas_index(WATER, &neighbourhood, 1, 1) - ATLAS_START
+}
+
+/// Mask with the corners that have a missing edge next to them cleared.
+fn reduced(
+    mask: u8
+) -> u8 {
+    let mut mask = mask;
+
+    for (corner, a, b) in [(NORTH_EAST, NORTH, EAST), (SOUTH_EAST, SOUTH, EAST), (SOUTH_WEST, SOUTH, WEST), (NORTH_WEST, NORTH, WEST)] {
+        if mask & a == 0 || mask & b == 0 {
+            mask &= !corner;
+        }
+    }
+
+    mask
+}
+
+#[test]
+fn reduced_masks_map_to_distinct_cells() {
+    let mut cells: HashMap<u8, usize> = HashMap::new();
+
+    for mask in 0..=255u8 {
+        let cell = blob_cell(mask);
+        assert!(cell < 47, "mask {:08b} maps to cell {}", mask, cell);
+
+        let reduced_cell = *cells.entry(reduced(mask)).or_insert(cell);
+        assert_eq!(cell, reduced_cell, "mask {:08b} and its reduced mask map to different cells", mask);
+    }
+
+    let mut distinct: Vec<usize> = cells.values().copied().collect();
+    distinct.sort();
+    distinct.dedup();
+
+    assert_eq!(cells.len(), 47);
+    assert_eq!(distinct, (0..47).collect::<Vec<_>>());
+}
+
+#[test]
+fn corners_without_both_edges_are_dropped() {
+    assert_eq!(blob_cell(NORTH | NORTH_EAST), blob_cell(NORTH));
+    assert_eq!(blob_cell(EAST | NORTH_EAST), blob_cell(EAST));
+    assert_eq!(blob_cell(NORTH_EAST), blob_cell(0));
+    assert_ne!(blob_cell(NORTH | EAST | NORTH_EAST), blob_cell(NORTH | EAST));
+}
+
+#[test]
+fn isolated_and_surrounded_tiles_use_the_first_and_last_cells() {
+    assert_eq!(blob_cell(0), 0);
+    assert_eq!(blob_cell(u8::MAX), 46);
+}
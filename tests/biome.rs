@@ -0,0 +1,113 @@
+use std::fs;
+use cli_mate::TerrainDefinition;
+use cli_mate::terrain::biome::{Biome, BiomeHandle, BiomeType, TileType, WorldGenError};
+use cli_mate::terrain::definition::TerrainDefinitionError;
+use cli_mate::terrain::foliage::{Foliage, FoliageType};
+use cli_mate::terrain::season::Season;
+
+fn load_definition() -> TerrainDefinition {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/terrain/default.terrain.ron");
+    TerrainDefinition::from_bytes(&fs::read(path).unwrap()).unwrap()
+}
+
+fn load_biome_handle() -> BiomeHandle {
+    BiomeHandle::from_definition(&load_definition()).unwrap()
+}
+
+fn rock(
+    spawns_on: Vec<usize>
+) -> Foliage {
+    Foliage {
+        id: "rock".to_string(),
+        foliage_type: FoliageType(0),
+        weight: 1,
+        scale: 1.0,
+        sprite: String::new(),
+        spawns_on
+    }
+}
+
+#[test]
+fn rng_of_one_picks_the_last_entry() {
+    let biome_handle = load_biome_handle();
+    let plains = biome_handle.biome_id("plains").unwrap();
+    let water = biome_handle.tile_id("water").unwrap();
+
+    assert_eq!(biome_handle.get_biome(plains).unwrap().get_tile_from_rng(1.0), Ok(water));
+    assert_eq!(
+        biome_handle.get_biome_from_rng(1.0),
+        Ok(BiomeType(biome_handle.biomes.len() as u16 - 1)));
+}
+
+#[test]
+fn rng_outside_of_zero_to_one_is_rejected() {
+    let biome_handle = load_biome_handle();
+    let biome = biome_handle.get_biome(BiomeType(0)).unwrap();
+
+    for rng in [-0.01, 1.01, f32::NAN] {
+        assert!(matches!(biome.get_tile_from_rng(rng), Err(WorldGenError::RngOutOfRange { .. })), "{}", rng);
+        assert!(matches!(biome_handle.get_biome_from_rng(rng), Err(WorldGenError::RngOutOfRange { .. })), "{}", rng);
+    }
+}
+
+#[test]
+fn unknown_biome_is_an_error() {
+    let biome_handle = load_biome_handle();
+    let biome = BiomeType(biome_handle.biomes.len() as u16);
+
+    assert!(matches!(biome_handle.get_biome(biome), Err(WorldGenError::UnknownBiome { biome: unknown }) if unknown == biome));
+}
+
+#[test]
+fn add_biome_rejects_zero_weights() {
+    let mut biome_handle = load_biome_handle();
+    let biome_type = BiomeType(biome_handle.biomes.len() as u16);
+
+    let biome = Biome::new("meadow", 0, biome_type, 0.5).add_tile(TileType::new(0, 1));
+    assert_eq!(biome_handle.add_biome(biome), Err(WorldGenError::ZeroWeight {
+        biome: "meadow".to_string(),
+        entry: None
+    }));
+
+    let biome = Biome::new("meadow", 1, biome_type, 0.5).add_tile(TileType::new(0, 0));
+    assert_eq!(biome_handle.add_biome(biome), Err(WorldGenError::ZeroWeight {
+        biome: "meadow".to_string(),
+        entry: Some(biome_handle.tiles[0].id.clone())
+    }));
+}
+
+#[test]
+fn add_biome_rejects_empty_tile_tables() {
+    let mut biome_handle = load_biome_handle();
+    let biome = Biome::new("meadow", 1, BiomeType(biome_handle.biomes.len() as u16), 0.5);
+
+    assert_eq!(biome_handle.add_biome(biome), Err(WorldGenError::EmptyTileTable {
+        biome: "meadow".to_string(),
+        season: None
+    }));
+
+    // Season tables keep the shape of the biome's table, so an empty one fails validation
+    let mut definition = load_definition();
+    let plains = definition.biomes.iter_mut().find(|biome| biome.id == "plains").unwrap();
+    let winter = plains.seasons.iter_mut().find(|season| season.season == Season::Winter).unwrap();
+    winter.tiles = Some(Vec::new());
+
+    assert_eq!(BiomeHandle::from_definition(&definition).err(), Some(TerrainDefinitionError::InvalidSeasonTiles {
+        biome: "plains".to_string(),
+        season: Season::Winter
+    }));
+}
+
+#[test]
+fn add_biome_rejects_foliage_on_unknown_tiles() {
+    let mut biome_handle = load_biome_handle();
+    let unknown_tile = biome_handle.tiles.len();
+    let biome = Biome::new("meadow", 1, BiomeType(biome_handle.biomes.len() as u16), 0.5)
+        .add_tile(TileType::new(0, 1))
+        .add_foliage(rock(vec![0, unknown_tile]));
+
+    assert_eq!(biome_handle.add_biome(biome), Err(WorldGenError::UnknownTile {
+        referenced_by: "rock".to_string(),
+        tile: unknown_tile
+    }));
+}
@@ -0,0 +1,248 @@
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+use bevy::prelude::*;
+use cli_mate::terrain::chunk::CHUNK_SIZE;
+use cli_mate::terrain::foliage::FoliageType;
+use cli_mate::terrain::noise::{DomainWarp, NoiseAlgorithm, NoiseLayer, NoiseSettings, get_noise};
+use cli_mate::terrain::season::Season;
+use cli_mate::terrain::seed::WorldSeed;
+use cli_mate::{ChunkData, TerrainDefinition, WorldGenerator};
+
+/// Set to rewrite the golden files from the current generation instead of comparing
+/// against them, i.e. `CLI_MATE_UPDATE_GOLDEN=1 cargo test --test generation`.
+const UPDATE_GOLDEN_ENV_VAR: &str = "CLI_MATE_UPDATE_GOLDEN";
+
+/// Chunks generated for every seed, around the origin and far from it.
+const CHUNKS: [IVec2; 5] = [
+    IVec2::new(0, 0),
+    IVec2::new(-1, -1),
+    IVec2::new(1, -1),
+    IVec2::new(5, -3),
+    IVec2::new(-40, 27),
+];
+
+fn load_generator(
+    seed: u32
+) -> WorldGenerator {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/terrain/default.terrain.ron");
+    let bytes = fs::read(path).expect("Failed to read terrain definition");
+    let definition = TerrainDefinition::from_bytes(&bytes).expect("Invalid terrain definition");
+
+    WorldGenerator::from_definition(WorldSeed::new(seed), &definition).expect("Invalid terrain definition")
+}
+
+/// Compare against `tests/golden/<name>.golden`, or rewrite it when `CLI_MATE_UPDATE_GOLDEN`
+/// is set.
+fn check_golden(
+    name: &str,
+    actual: &str
+) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.golden", name));
+
+    if env::var_os(UPDATE_GOLDEN_ENV_VAR).is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("Missing {}, run with {}=1 to create it", path.display(), UPDATE_GOLDEN_ENV_VAR))
+        .replace("\r\n", "\n");
+
+    if expected == actual {
+        return;
+    }
+
+    let (line, (expected_line, actual_line)) = expected.lines().chain(std::iter::repeat(""))
+        .zip(actual.lines().chain(std::iter::repeat("")))
+        .enumerate()
+        .find(|(_, (expected, actual))| expected != actual)
+        .unwrap();
+
+    panic!("Generation no longer matches {} at line {}\n  expected: {}\n  actual:   {}\n\
+            Run with {}=1 to update the golden files if the change is intended",
+           path.display(), line + 1, expected_line, actual_line, UPDATE_GOLDEN_ENV_VAR);
+}
+
+/// Index of an id in a layer's legend as a single character.
+fn cell(
+    index: usize
+) -> char {
+    char::from_digit(index as u32, 36).expect("Too many ids for a single character")
+}
+
+/// Write the tile, biome and foliage arrays of a chunk as grids.
+fn write_chunk(
+    out: &mut String,
+    chunk: &ChunkData
+) {
+    writeln!(out, "chunk {},{}", chunk.coordinate.x, chunk.coordinate.y).unwrap();
+
+    write_grid(out, "tiles", |x, y| cell(chunk.tiles[x][y].tile));
+    write_grid(out, "biomes", |x, y| cell(chunk.tiles[x][y].biome.0 as usize));
+    write_grid(out, "foliage", |x, y| match chunk.foliage_type[x][y] {
+        FoliageType::NONE => '.',
+        foliage => cell(foliage.0 as usize)
+    });
+
+    out.push('\n');
+}
+
+/// Write a layer of a chunk one row per line, north up.
+fn write_grid(
+    out: &mut String,
+    name: &str,
+    layer: impl Fn(usize, usize) -> char
+) {
+    writeln!(out, "{}", name).unwrap();
+
+    for y in (0..CHUNK_SIZE).rev() {
+        let row: String = (0..CHUNK_SIZE).map(|x| layer(x, y)).collect();
+        writeln!(out, "{}", row).unwrap();
+    }
+}
+
+fn chunks_snapshot(
+    generator: &WorldGenerator
+) -> String {
+    let biome_handle = generator.biome_handle();
+    let legend = |ids: Vec<&String>| ids.iter().enumerate()
+        .map(|(index, id)| format!("{}={}", cell(index), id))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut out = String::new();
+    writeln!(out, "seed {} season {:?}", generator.seed().seed, biome_handle.season).unwrap();
+    writeln!(out, "tiles: {}", legend(biome_handle.tiles.iter().map(|tile| &tile.id).collect())).unwrap();
+    writeln!(out, "biomes: {}", legend(biome_handle.biomes.iter().map(|biome| &biome.id).collect())).unwrap();
+    writeln!(out, "foliage: {}", legend(biome_handle.foliage.iter().map(|foliage| &foliage.id).collect())).unwrap();
+    out.push('\n');
+
+    for coordinate in CHUNKS {
+        write_chunk(&mut out, &generator.generate_chunk(coordinate).unwrap());
+    }
+
+    out
+}
+
+#[test]
+fn chunks_match_golden_seed_0() {
+    check_golden("chunks_seed_0", &chunks_snapshot(&load_generator(0)));
+}
+
+#[test]
+fn chunks_match_golden_seed_1234() {
+    check_golden("chunks_seed_1234", &chunks_snapshot(&load_generator(1234)));
+}
+
+#[test]
+fn chunks_match_golden_seed_max() {
+    check_golden("chunks_seed_max", &chunks_snapshot(&load_generator(u32::MAX)));
+}
+
+#[test]
+fn winter_chunks_match_golden() {
+    let mut generator = load_generator(1234);
+    generator.set_season(Season::Winter);
+
+    check_golden("chunks_seed_1234_winter", &chunks_snapshot(&generator));
+}
+
+#[test]
+fn generation_is_deterministic() {
+    let first = load_generator(99);
+    let second = load_generator(99);
+
+    for coordinate in CHUNKS {
+        assert_eq!(
+            first.generate_chunk(coordinate).unwrap(),
+            second.generate_chunk(coordinate).unwrap(),
+            "chunk {} differs between generators", coordinate);
+    }
+}
+
+#[test]
+fn noise_matches_golden() {
+    let mut out = String::new();
+
+    let layers = [
+        (Vec2::new(0.0, 0.0), 0, NoiseSettings::new(0.7, 5)),
+        (Vec2::new(-3.0, 7.0), 1234, NoiseSettings::new(5.0, 5)),
+        (Vec2::new(12.0, -9.0), u32::MAX, NoiseSettings::new(0.1, 3)),
+        (Vec2::new(2.0, 3.0), 42, NoiseSettings {
+            algorithm: NoiseAlgorithm::Simplex,
+            persistence: 0.6,
+            lacunarity: 1.8,
+            offset: [0.5, -2.0],
+            ..NoiseSettings::new(0.5, 4)
+        }),
+        (Vec2::new(-1.0, 0.0), 7, NoiseSettings {
+            algorithm: NoiseAlgorithm::Worley,
+            warp: Some(DomainWarp { strength: 0.5, frequency: 0.3 }),
+            ..NoiseSettings::new(1.0, 2)
+        }),
+    ];
+
+    for (coordinate, seed, settings) in layers {
+        writeln!(out, "noise {},{} seed {} {:?}", coordinate.x, coordinate.y, seed, settings).unwrap();
+        let noise = get_noise(coordinate, seed, &NoiseLayer::new(settings));
+
+        for y in (0..CHUNK_SIZE).rev() {
+            let row: Vec<String> = (0..CHUNK_SIZE).map(|x| format!("{:.5}", noise[x][y])).collect();
+            writeln!(out, "{}", row.join(" ")).unwrap();
+        }
+
+        out.push('\n');
+    }
+
+    check_golden("noise", &out);
+}
+
+/// Equalised noise is uniform, every tenth of [0, 1) holds close to a tenth of the values.
+#[test]
+fn equalized_noise_is_uniform() {
+    let layer = NoiseLayer::new(NoiseSettings {
+        equalize: true,
+        ..NoiseSettings::new(0.7, 5)
+    });
+
+    let mut bins = [0usize; 10];
+    let mut samples = 0;
+
+    for x in -8..8 {
+        for y in -8..8 {
+            let noise = get_noise(Vec2::new(x as f32, y as f32), 1234, &layer);
+
+            for value in noise.iter().flatten() {
+                bins[(*value * bins.len() as f32) as usize] += 1;
+                samples += 1;
+            }
+        }
+    }
+
+    for (bin, count) in bins.iter().enumerate() {
+        let share = *count as f32 / samples as f32;
+        assert!((share - 0.1).abs() < 0.02, "bin {} holds {:.3} of the values", bin, share);
+    }
+}
+
+#[test]
+fn biome_picks_match_golden() {
+    let generator = load_generator(0);
+    let biome_handle = generator.biome_handle();
+    let mut out = String::new();
+
+    for step in 0..64 {
+        let rng = step as f32 / 64.0;
+        let biome = biome_handle.get_biome(biome_handle.get_biome_from_rng(rng).unwrap()).unwrap();
+        let tile = biome.get_tile_from_rng(rng).unwrap();
+
+        writeln!(out, "{:.4} {} {}", rng, biome.id, biome_handle.tiles[tile].id).unwrap();
+    }
+
+    check_golden("biome_picks", &out);
+}
@@ -0,0 +1,75 @@
+use cli_mate::terrain::chunk::CHUNK_SIZE;
+use cli_mate::terrain::meshing::{greedy_quads, TileQuad};
+
+type Grid = [[u8; CHUNK_SIZE]; CHUNK_SIZE];
+
+fn grid(
+    tile: impl Fn(usize, usize) -> u8
+) -> Grid {
+    let mut tiles = [[0; CHUNK_SIZE]; CHUNK_SIZE];
+
+    for (x, column) in tiles.iter_mut().enumerate() {
+        for (y, value) in column.iter_mut().enumerate() {
+            *value = tile(x, y);
+        }
+    }
+
+    tiles
+}
+
+/// Check every tile is covered by exactly one quad holding its own value.
+fn assert_exact_cover(
+    tiles: &Grid,
+    quads: &[TileQuad<u8>]
+) {
+    let mut covered = [[0; CHUNK_SIZE]; CHUNK_SIZE];
+
+    for quad in quads {
+        assert!(quad.width > 0 && quad.height > 0, "empty quad {:?}", quad);
+        assert!(quad.x + quad.width <= CHUNK_SIZE && quad.y + quad.height <= CHUNK_SIZE, "quad {:?} leaves the chunk", quad);
+
+        for x in quad.x..quad.x + quad.width {
+            for y in quad.y..quad.y + quad.height {
+                assert_eq!(tiles[x][y], quad.tile, "quad {:?} merges tile {},{} of another value", quad, x, y);
+                covered[x][y] += 1;
+            }
+        }
+    }
+
+    for (x, column) in covered.iter().enumerate() {
+        for (y, count) in column.iter().enumerate() {
+            assert_eq!(*count, 1, "tile {},{} is covered {} times", x, y, count);
+        }
+    }
+}
+
+#[test]
+fn full_grid_is_a_single_quad() {
+    let tiles = grid(|_, _| 3);
+    let quads = greedy_quads(&tiles);
+
+    assert_exact_cover(&tiles, &quads);
+    assert_eq!(quads, vec![TileQuad { x: 0, y: 0, width: CHUNK_SIZE, height: CHUNK_SIZE, tile: 3 }]);
+}
+
+#[test]
+fn checkerboard_never_merges() {
+    let tiles = grid(|x, y| ((x + y) % 2) as u8);
+    let quads = greedy_quads(&tiles);
+
+    assert_exact_cover(&tiles, &quads);
+    assert_eq!(quads.len(), CHUNK_SIZE * CHUNK_SIZE);
+}
+
+#[test]
+fn l_shape_is_split_into_its_row_and_column() {
+    let tiles = grid(|x, y| (x == 0 || y == 0) as u8);
+    let quads = greedy_quads(&tiles);
+
+    assert_exact_cover(&tiles, &quads);
+    assert_eq!(quads, vec![
+        TileQuad { x: 0, y: 0, width: CHUNK_SIZE, height: 1, tile: 1 },
+        TileQuad { x: 0, y: 1, width: 1, height: CHUNK_SIZE - 1, tile: 1 },
+        TileQuad { x: 1, y: 1, width: CHUNK_SIZE - 1, height: CHUNK_SIZE - 1, tile: 0 },
+    ]);
+}
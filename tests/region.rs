@@ -0,0 +1,98 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use bevy::prelude::IVec2;
+use cli_mate::TerrainDefinition;
+use cli_mate::terrain::biome::BiomeHandle;
+use cli_mate::terrain::chunk::{Chunk, CHUNK_SIZE};
+use cli_mate::terrain::foliage::FoliageType;
+use cli_mate::terrain::region::{ChunkSave, REGION_FORMAT_VERSION, RegionStore};
+use cli_mate::terrain::tile::Tile;
+
+/// Empty directory for a test's region files.
+fn region_directory(
+    name: &str
+) -> PathBuf {
+    let directory = env::temp_dir().join(format!("cli_mate-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+
+    directory
+}
+
+fn chunk_save(
+    tile: usize
+) -> ChunkSave {
+    ChunkSave {
+        tile_ids: vec!["dirt".to_string(), "grass".to_string()],
+        biome_ids: vec!["plains".to_string()],
+        foliage_ids: vec!["rock".to_string()],
+        tiles: [[Tile { tile, ..Tile::default() }; CHUNK_SIZE]; CHUNK_SIZE],
+        foliage_type: [[FoliageType::NONE; CHUNK_SIZE]; CHUNK_SIZE]
+    }
+}
+
+#[test]
+fn corrupt_region_is_set_aside_on_save() {
+    let directory = region_directory("corrupt-region");
+    let region_store = RegionStore::new(&directory);
+    let coordinate = IVec2::new(3, -2);
+    let path = region_store.region_path(RegionStore::region_coordinate(coordinate));
+
+    fs::write(&path, b"not a region file").unwrap();
+    assert!(region_store.load_chunk(coordinate).is_err());
+
+    region_store.save_chunk(coordinate, chunk_save(1)).unwrap();
+
+    assert_eq!(fs::read(path.with_extension("bin.corrupt")).unwrap(), b"not a region file");
+    let loaded = region_store.load_chunk(coordinate).unwrap().expect("chunk was saved");
+    assert_eq!(loaded.tiles[0][0].tile, 1);
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+/// `tests/fixtures/region_v1.bin` holds chunks 0,0 and 1,2 of region 0,0 written by the
+/// version 1 format, with tile `(x + y + chunk index) % 5` of dirt, grass, stone, sand and
+/// water, biome `x % 5` of plains, desert, grassland, beach and ocean, and foliage `y % 4`
+/// of none, cactus, rose and rock.
+#[test]
+fn version_1_region_is_migrated() {
+    let directory = region_directory("region-v1");
+    let region_store = RegionStore::new(&directory);
+    fs::copy(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/region_v1.bin"),
+        region_store.region_path(IVec2::ZERO)).unwrap();
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/terrain/default.terrain.ron");
+    let definition = TerrainDefinition::from_bytes(&fs::read(path).unwrap()).unwrap();
+    let biome_handle = BiomeHandle::from_definition(&definition).unwrap();
+
+    let tiles = ["dirt", "grass", "stone", "sand", "water"];
+    let biomes = ["plains", "desert", "grassland", "beach", "ocean"];
+    let foliage = [None, Some("cactus"), Some("rose"), Some("rock")];
+
+    for (coordinate, index) in [(IVec2::new(0, 0), 0), (IVec2::new(1, 2), 33)] {
+        let chunk_save = region_store.load_chunk(coordinate).unwrap().expect("chunk is in the fixture");
+        let chunk = Chunk::from_save(coordinate, chunk_save, &biome_handle);
+
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                let tile = chunk.tiles[x][y];
+
+                assert_eq!(biome_handle.tiles[tile.tile].id, tiles[(x + y + index) % 5]);
+                assert_eq!(biome_handle.get_biome(tile.biome).unwrap().id, biomes[x % 5]);
+                assert_eq!(
+                    biome_handle.get_foliage(chunk.foliage_type[x][y]).map(|foliage| foliage.id.as_str()),
+                    foliage[y % 4]);
+            }
+        }
+    }
+
+    // Saving into the migrated region writes it back in the current format
+    region_store.save_chunk(IVec2::new(2, 0), chunk_save(1)).unwrap();
+    assert_eq!(fs::read(region_store.region_path(IVec2::ZERO)).unwrap()[..4], REGION_FORMAT_VERSION.to_le_bytes());
+    assert!(region_store.load_chunk(IVec2::new(1, 2)).unwrap().is_some());
+
+    fs::remove_dir_all(directory).unwrap();
+}
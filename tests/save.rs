@@ -0,0 +1,24 @@
+use cli_mate::clock::START_TIME_OF_DAY;
+use cli_mate::save::{migrate, SAVE_FORMAT_VERSION, SaveGame, SaveMetadata, WorldSave};
+
+#[test]
+fn version_1_save_resumes_on_the_first_morning() {
+    let world: WorldSave = ron::from_str("(player: (pos: (3.0, -4.0), vel: (0.0, 0.0)), camera_zoom: 0.5)").unwrap();
+    assert_eq!((world.day, world.time_of_day), (0, 0.0));
+
+    let save_game = migrate(SaveGame {
+        metadata: SaveMetadata {
+            version: 1,
+            name: "world".to_string(),
+            seed: 1234,
+            saved_at: 0
+        },
+        world
+    }).unwrap();
+
+    assert_eq!(save_game.metadata.version, SAVE_FORMAT_VERSION);
+    assert_eq!(save_game.metadata.seed, 1234);
+    assert_eq!(save_game.world.player.pos, [3.0, -4.0]);
+    assert_eq!((save_game.world.day, save_game.world.time_of_day), (0, START_TIME_OF_DAY));
+}
+
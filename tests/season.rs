@@ -0,0 +1,109 @@
+use std::fs;
+use bevy::prelude::IVec2;
+use cli_mate::terrain::biome::BiomeHandle;
+use cli_mate::terrain::chunk::{Chunk, CHUNK_SIZE};
+use cli_mate::terrain::definition::TerrainDefinitionError;
+use cli_mate::terrain::foliage::FoliageType;
+use cli_mate::terrain::season::{Season, SeasonalUpdate};
+use cli_mate::terrain::seed::WorldSeed;
+use cli_mate::{TerrainDefinition, WorldGenerator};
+
+fn load_definition() -> TerrainDefinition {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/terrain/default.terrain.ron");
+    TerrainDefinition::from_bytes(&fs::read(path).unwrap()).unwrap()
+}
+
+fn season_handles(
+    generator: &WorldGenerator
+) -> Vec<BiomeHandle> {
+    Season::ALL.iter()
+        .map(|season| generator.biome_handle().for_season(*season))
+        .collect()
+}
+
+fn seasonal_update(
+    generator: &WorldGenerator,
+    chunk: &Chunk,
+    season: Season
+) -> SeasonalUpdate {
+    Chunk::seasonal_update(
+        chunk.coordinate,
+        chunk.tiles,
+        chunk.foliage_type,
+        chunk.season,
+        season,
+        generator.seed(),
+        &season_handles(generator)).unwrap()
+}
+
+/// First spring chunk near the origin with tiles that change in winter.
+fn chunk_changing_in_winter(
+    generator: &WorldGenerator
+) -> Chunk {
+    (0..64)
+        .map(|index| Chunk::from_data(generator.generate_chunk(IVec2::new(index % 8, index / 8)).unwrap()))
+        .find(|chunk| !seasonal_update(generator, chunk, Season::Winter).changes.is_empty())
+        .expect("no chunk near the origin changes in winter")
+}
+
+fn tile_types(
+    chunk: &Chunk
+) -> [[usize; CHUNK_SIZE]; CHUNK_SIZE] {
+    chunk.tiles.map(|column| column.map(|tile| tile.tile))
+}
+
+#[test]
+fn unchanged_chunk_has_no_changes() {
+    let generator = WorldGenerator::from_definition(WorldSeed::new(1234), &load_definition()).unwrap();
+    let chunk = chunk_changing_in_winter(&generator);
+
+    assert_eq!(chunk.season, Some(Season::Spring));
+    assert!(seasonal_update(&generator, &chunk, Season::Spring).changes.is_empty());
+}
+
+#[test]
+fn player_placed_tile_survives_season_change() {
+    let generator = WorldGenerator::from_definition(WorldSeed::new(1234), &load_definition()).unwrap();
+    let mut chunk = chunk_changing_in_winter(&generator);
+    let change = seasonal_update(&generator, &chunk, Season::Winter).changes[0];
+
+    // Sand does not grow in the biomes that snow over, so it was placed by the player
+    let sand = generator.biome_handle().tiles.iter().position(|tile| tile.id == "sand").unwrap();
+    chunk.tiles[change.x][change.y].tile = sand;
+    chunk.foliage_type[change.x][change.y] = FoliageType::NONE;
+
+    let update = seasonal_update(&generator, &chunk, Season::Winter);
+    assert!(update.changes.iter().all(|other| (other.x, other.y) != (change.x, change.y)));
+}
+
+#[test]
+fn spring_winter_spring_restores_the_original_tiles() {
+    let generator = WorldGenerator::from_definition(WorldSeed::new(1234), &load_definition()).unwrap();
+    let mut chunk = chunk_changing_in_winter(&generator);
+    let original = (tile_types(&chunk), chunk.foliage_type);
+
+    let mut change_season = |season: Season| {
+        for change in seasonal_update(&generator, &chunk, season).changes {
+            assert!(chunk.apply_seasonal_change(change).is_some());
+        }
+        chunk.season = Some(season);
+
+        (tile_types(&chunk), chunk.foliage_type)
+    };
+
+    assert_ne!(change_season(Season::Winter), original);
+    assert_eq!(change_season(Season::Spring), original);
+}
+
+#[test]
+fn season_tiles_must_keep_the_biome_weights() {
+    let mut definition = load_definition();
+    let plains = definition.biomes.iter_mut().find(|biome| biome.id == "plains").unwrap();
+    let winter = plains.seasons.iter_mut().find(|season| season.season == Season::Winter).unwrap();
+    winter.tiles.as_mut().unwrap().swap(0, 1);
+
+    assert_eq!(definition.validate().err(), Some(TerrainDefinitionError::InvalidSeasonTiles {
+        biome: "plains".to_string(),
+        season: Season::Winter
+    }));
+}
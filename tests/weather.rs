@@ -0,0 +1,96 @@
+use std::fs;
+use cli_mate::TerrainDefinition;
+use cli_mate::terrain::biome::BiomeHandle;
+use cli_mate::terrain::definition::{WeatherTransitionDefinition, WeatherWeightDefinition};
+use cli_mate::terrain::season::Season;
+use cli_mate::terrain::seed::WorldSeed;
+use cli_mate::weather::{BiomeWeather, Weather, WeatherChain, MELTING_SECONDS, SNOWING_SECONDS};
+
+fn transition(
+    from: Weather,
+    to: &[(Weather, u16)]
+) -> WeatherTransitionDefinition {
+    WeatherTransitionDefinition {
+        from,
+        to: to.iter()
+            .map(|&(weather, weight)| WeatherWeightDefinition { weather, weight })
+            .collect()
+    }
+}
+
+#[test]
+fn next_weather_follows_the_weights() {
+    let chain = WeatherChain::from_definition(&[
+        transition(Weather::Clear, &[(Weather::Clear, 3), (Weather::Rain, 1)]),
+        transition(Weather::Rain, &[(Weather::Rain, 1), (Weather::Snow, 1)])
+    ]);
+
+    assert_eq!(chain.next(Weather::Clear, 0.0), Weather::Clear);
+    assert_eq!(chain.next(Weather::Clear, 0.74), Weather::Clear);
+    assert_eq!(chain.next(Weather::Clear, 0.75), Weather::Rain);
+    assert_eq!(chain.next(Weather::Clear, 0.999), Weather::Rain);
+
+    assert_eq!(chain.next(Weather::Rain, 0.49), Weather::Rain);
+    assert_eq!(chain.next(Weather::Rain, 0.5), Weather::Snow);
+
+    let rainy = (0..1000)
+        .filter(|step| chain.next(Weather::Clear, *step as f32 / 1000.0) == Weather::Rain)
+        .count();
+    assert_eq!(rainy, 250);
+}
+
+#[test]
+fn zero_total_weight_clears_up() {
+    let chain = WeatherChain::from_definition(&[
+        transition(Weather::Rain, &[(Weather::Rain, 0), (Weather::Snow, 0)])
+    ]);
+
+    assert_eq!(chain.next(Weather::Rain, 0.0), Weather::Clear);
+    assert_eq!(chain.next(Weather::Rain, 0.5), Weather::Clear);
+}
+
+#[test]
+fn weather_without_transitions_clears_up() {
+    let chain = WeatherChain::from_definition(&[
+        transition(Weather::Clear, &[(Weather::Sandstorm, 1)])
+    ]);
+
+    assert_eq!(chain.next(Weather::Sandstorm, 0.5), Weather::Clear);
+    assert_eq!(WeatherChain::default().next(Weather::Rain, 0.5), Weather::Clear);
+}
+
+#[test]
+fn ground_weather_carries_over_a_season_change() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/terrain/default.terrain.ron");
+    let definition = TerrainDefinition::from_bytes(&fs::read(path).unwrap()).unwrap();
+    let winter = BiomeHandle::from_definition(&definition).unwrap().for_season(Season::Winter);
+    let spring = winter.for_season(Season::Spring);
+    let seed = WorldSeed::new(1234);
+
+    // Snow over every biome through the winter
+    let mut biome_weather = BiomeWeather::default();
+    biome_weather.advance_to(100, &winter, &seed);
+    biome_weather.weather.fill(Weather::Rain);
+    biome_weather.update_ground(SNOWING_SECONDS, Season::Winter);
+    assert!(biome_weather.snow_cover.iter().all(|snow_cover| *snow_cover == 1.0));
+
+    biome_weather.weather.fill(Weather::Clear);
+    biome_weather.advance_to(101, &spring, &seed);
+    biome_weather.update_ground(MELTING_SECONDS / 4.0, Season::Spring);
+
+    // The chain carries on from the winter and the snow melts gradually
+    assert_eq!(biome_weather.step, 101);
+
+    for (weather, snow_cover) in biome_weather.weather.iter().zip(&biome_weather.snow_cover) {
+        match weather {
+            Weather::Snow => assert_eq!(*snow_cover, 1.0),
+            _ => assert_eq!(*snow_cover, 0.75)
+        }
+    }
+
+    // Replaying the chains keeps the ground
+    let snow_cover = biome_weather.snow_cover.clone();
+    biome_weather.restart();
+    biome_weather.advance_to(101, &spring, &seed);
+    assert_eq!(biome_weather.snow_cover, snow_cover);
+}